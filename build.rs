@@ -0,0 +1,105 @@
+fn main() {
+    #[cfg(feature = "embed")]
+    embed::generate();
+}
+
+/// Embeds the built frontend, see `src/assets.rs`
+#[cfg(feature = "embed")]
+mod embed {
+    use sha2::{Digest, Sha256};
+    use std::{
+        env,
+        fmt::Write as _,
+        fs,
+        io::Write as _,
+        path::{Path, PathBuf},
+    };
+
+    /// Compressed variants are only kept if they save at least this fraction
+    const MIN_SAVING: f64 = 0.1;
+
+    pub fn generate() {
+        println!("cargo:rerun-if-env-changed=EMBED_VIEW");
+        let view = PathBuf::from(env::var("EMBED_VIEW").unwrap_or_else(|_| "view/build".into()));
+        let view = fs::canonicalize(&view).unwrap_or_else(|_| {
+            panic!("The built frontend {view:?} does not exist, run `bun run build` in view first")
+        });
+        println!("cargo:rerun-if-changed={}", view.display());
+
+        let out = PathBuf::from(env::var("OUT_DIR").unwrap());
+        let compressed = out.join("assets");
+        fs::create_dir_all(&compressed).unwrap();
+
+        let mut files = Vec::new();
+        collect(&view, &mut files);
+        let mut files: Vec<(String, PathBuf)> = files
+            .into_iter()
+            .map(|file| {
+                let path = file.strip_prefix(&view).unwrap().to_string_lossy();
+                (path.replace('\\', "/"), file)
+            })
+            .collect();
+        files.sort();
+
+        let mut code = String::from("&[\n");
+        for (i, (path, file)) in files.iter().enumerate() {
+            println!("cargo:rerun-if-changed={}", file.display());
+            let raw = fs::read(file).unwrap();
+            let hash = Sha256::digest(&raw);
+            let etag = hash[..16].iter().fold(String::new(), |mut s, b| {
+                write!(s, "{b:02x}").unwrap();
+                s
+            });
+            let content_type = mime_guess::from_path(file).first_or_octet_stream();
+
+            let gzip = compress(&raw, &compressed.join(format!("{i}.gz")), |raw, out| {
+                let mut encoder = flate2::write::GzEncoder::new(out, flate2::Compression::best());
+                encoder.write_all(raw).unwrap();
+                encoder.finish().unwrap();
+            });
+            let brotli = compress(&raw, &compressed.join(format!("{i}.br")), |raw, out| {
+                let mut encoder = brotli::CompressorWriter::new(out, 4096, 11, 22);
+                encoder.write_all(raw).unwrap();
+            });
+
+            writeln!(
+                code,
+                "    Asset {{ path: {path:?}, content_type: {content_type:?}, etag: \"\\\"{etag}\\\"\", \
+                 raw: include_bytes!({file:?}), gzip: {gzip}, brotli: {brotli} }},",
+                content_type = content_type.as_ref(),
+            )
+            .unwrap();
+        }
+        code.push(']');
+        fs::write(out.join("assets.rs"), code).unwrap();
+    }
+
+    /// Recursively lists all files of a directory
+    fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                collect(&path, files);
+            } else if !is_precompressed(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    /// Precompressed siblings of the build are skipped, the variants are generated here
+    fn is_precompressed(path: &Path) -> bool {
+        matches!(path.extension(), Some(ext) if ext == "br" || ext == "gz")
+            && path.with_extension("").exists()
+    }
+
+    /// Writes the compressed file and returns the expression including it, if worthwhile
+    fn compress(raw: &[u8], path: &Path, encode: impl FnOnce(&[u8], &mut Vec<u8>)) -> String {
+        let mut out = Vec::new();
+        encode(raw, &mut out);
+        if (out.len() as f64) > raw.len() as f64 * (1.0 - MIN_SAVING) {
+            return "None".into();
+        }
+        fs::write(path, &out).unwrap();
+        format!("Some(include_bytes!({path:?}))")
+    }
+}
@@ -0,0 +1,405 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use aws_lc_rs::rand::{SecureRandom, SystemRandom};
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+    sync::{Arc, LazyLock},
+};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{error, info, warn};
+
+use crate::{
+    name::{self, NameError},
+    net::ClientInfo,
+    util,
+};
+
+/// Name of the session cookie
+pub const SESSION_COOKIE: &str = "session";
+/// Seconds a session stays valid
+const SESSION_TTL: u64 = 30 * 24 * 60 * 60;
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_PASSWORD_LENGTH: usize = 128;
+/// Failed logins per address and per username before logins are refused
+const MAX_LOGIN_FAILURES: u32 = 5;
+/// Registrations per address before registering is refused
+const MAX_REGISTRATIONS: u32 = 5;
+/// Registrations of all addresses before registering is refused
+const MAX_GLOBAL_REGISTRATIONS: u32 = 100;
+/// Seconds until failed logins and registrations are forgotten
+const THROTTLE_WINDOW: u64 = 5 * 60;
+
+/// Hash verified for unknown usernames, so they take as long as known ones
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| hash_password("not a password"));
+
+/// A registered user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub username: String,
+    /// Argon2 hash in the PHC string format
+    pub password_hash: String,
+    pub created_at: u64,
+}
+
+impl Account {
+    /// Identity under which the contributions of the account are stored,
+    /// cannot collide with client generated identities
+    pub fn identity(username: &str) -> String {
+        format!("account:{}", username.to_lowercase())
+    }
+
+    /// Returns true if the identity belongs to an account
+    pub fn is_identity(identity: &str) -> bool {
+        identity.starts_with("account:")
+    }
+}
+
+/// All registered users by lowercase username
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Accounts {
+    pub accounts: HashMap<String, Account>,
+}
+
+impl Accounts {
+    /// Loads the accounts from a json file
+    pub fn load_from_file(&mut self, path: &PathBuf) {
+        if path.exists() {
+            let file = File::open(path).unwrap();
+            let reader = BufReader::new(file);
+            *self = serde_json::from_reader(reader).unwrap();
+            info!("Loaded Accounts from file: {:?}", path);
+        }
+    }
+
+    /// Saves the accounts to a json file
+    pub fn save_to_file(&self, path: &PathBuf) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
+
+/// A logged in browser
+#[derive(Debug, Clone)]
+struct Session {
+    username: String,
+    expires: u64,
+}
+
+/// Failed logins or registrations of an address or username
+#[derive(Debug, Clone, Copy)]
+struct Attempts {
+    count: u32,
+    /// Unix time in seconds of the first attempt
+    since: u64,
+}
+
+/// Accounts with their persistence and the in-memory sessions
+#[derive(Debug)]
+pub struct AccountStore {
+    accounts: RwLock<Accounts>,
+    sessions: RwLock<HashMap<String, Session>>,
+    /// Failed logins and registrations by address and username
+    attempts: RwLock<HashMap<String, Attempts>>,
+    /// Held while saving, so older snapshots never overwrite newer ones
+    saving: Mutex<()>,
+    path: PathBuf,
+}
+
+impl AccountStore {
+    pub fn new(accounts: Accounts, path: PathBuf) -> Self {
+        Self {
+            accounts: RwLock::new(accounts),
+            sessions: RwLock::new(HashMap::new()),
+            attempts: RwLock::new(HashMap::new()),
+            saving: Mutex::new(()),
+            path,
+        }
+    }
+
+    /// Returns the username of a valid session
+    pub async fn username(&self, token: &str, now: u64) -> Option<String> {
+        let sessions = self.sessions.read().await;
+        sessions
+            .get(token)
+            .filter(|session| session.expires > now)
+            .map(|session| session.username.clone())
+    }
+
+    /// Returns the username of the session cookie of a request
+    pub async fn session_user(&self, headers: &HeaderMap) -> Option<String> {
+        let token = session_cookie(headers)?;
+        self.username(token, util::now_secs()).await
+    }
+
+    /// Returns true if one of the keys made `limit` attempts recently
+    async fn is_throttled(&self, keys: &[String], limit: u32, now: u64) -> bool {
+        let attempts = self.attempts.read().await;
+        keys.iter().any(|key| {
+            attempts.get(key).is_some_and(|attempts| {
+                attempts.count >= limit && attempts.since + THROTTLE_WINDOW > now
+            })
+        })
+    }
+
+    /// Records an attempt of the keys
+    async fn record_attempt(&self, keys: &[String], now: u64) {
+        let mut attempts = self.attempts.write().await;
+        attempts.retain(|_, attempts| attempts.since + THROTTLE_WINDOW > now);
+        for key in keys {
+            attempts
+                .entry(key.clone())
+                .or_insert(Attempts {
+                    count: 0,
+                    since: now,
+                })
+                .count += 1;
+        }
+    }
+
+    /// Saves the accounts in the background, without holding their lock while writing
+    async fn save(&self) {
+        let _saving = self.saving.lock().await;
+        let accounts = self.accounts.read().await.clone();
+        let path = self.path.clone();
+        let result = tokio::task::spawn_blocking(move || accounts.save_to_file(&path))
+            .await
+            .unwrap();
+        if let Err(e) = result {
+            error!("Error saving accounts: {}", e);
+        }
+    }
+
+    /// Starts a new session and returns its token
+    async fn login(&self, username: String, now: u64) -> String {
+        let mut token = [0; 32];
+        SystemRandom::new().fill(&mut token).unwrap();
+        let token = URL_SAFE_NO_PAD.encode(token);
+
+        let mut sessions = self.sessions.write().await;
+        sessions.retain(|_, session| session.expires > now);
+        sessions.insert(
+            token.clone(),
+            Session {
+                username,
+                expires: now + SESSION_TTL,
+            },
+        );
+        token
+    }
+}
+
+/// Returns the value of the session cookie
+fn session_cookie(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value)
+}
+
+/// Builds the `Set-Cookie` header of a session, an empty token removes it
+fn set_cookie(token: &str, secure: bool) -> HeaderValue {
+    let max_age = if token.is_empty() { 0 } else { SESSION_TTL };
+    let secure = if secure { "; Secure" } else { "" };
+    HeaderValue::try_from(format!(
+        "{SESSION_COOKIE}={token}; Path=/; Max-Age={max_age}; HttpOnly; SameSite=Lax{secure}"
+    ))
+    .unwrap()
+}
+
+/// Validates a username, like nicknames but without spaces
+pub fn validate_username(username: &str) -> Result<String, NameError> {
+    let username = name::validate(username)?;
+    if username.contains(' ') {
+        return Err(NameError::InvalidCharacter(' '));
+    }
+    Ok(username)
+}
+
+/// Hashes a password with a random salt, blocking for a while
+fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string()
+}
+
+/// Verifies a password against its hash, blocking for a while
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+/// Body of `POST /api/register` and `POST /api/login`
+#[derive(Debug, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// The logged in user
+#[derive(Debug, Serialize)]
+pub struct Me {
+    pub username: String,
+}
+
+/// Handler for `POST /api/register`, creates an account and logs it in
+pub async fn register(
+    State(store): State<Arc<AccountStore>>,
+    Extension(info): Extension<ClientInfo>,
+    Json(credentials): Json<Credentials>,
+) -> Response {
+    let username = match validate_username(&credentials.username) {
+        Ok(username) => username,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let length = credentials.password.chars().count();
+    if !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&length) {
+        let message =
+            format!("password must have {MIN_PASSWORD_LENGTH} to {MAX_PASSWORD_LENGTH} characters");
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+
+    let key = username.to_lowercase();
+    if store.accounts.read().await.accounts.contains_key(&key) {
+        return StatusCode::CONFLICT.into_response();
+    }
+    // Every registration hashes, so they are limited per address and overall
+    let now = util::now_secs();
+    let global = ["register".to_string()];
+    let address = info.ip.map(|ip| format!("register:{ip}"));
+    if store
+        .is_throttled(address.as_slice(), MAX_REGISTRATIONS, now)
+        .await
+        || store
+            .is_throttled(&global, MAX_GLOBAL_REGISTRATIONS, now)
+            .await
+    {
+        return StatusCode::TOO_MANY_REQUESTS.into_response();
+    }
+    store
+        .record_attempt(&[global.as_slice(), address.as_slice()].concat(), now)
+        .await;
+    let password = credentials.password;
+    let password_hash = tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .unwrap();
+
+    {
+        let mut accounts = store.accounts.write().await;
+        // Checked again, another registration may have won while hashing
+        if accounts.accounts.contains_key(&key) {
+            return StatusCode::CONFLICT.into_response();
+        }
+        accounts.accounts.insert(
+            key,
+            Account {
+                username: username.clone(),
+                password_hash,
+                created_at: now,
+            },
+        );
+    }
+    store.save().await;
+    info!("Registered account {}", username);
+
+    let token = store.login(username.clone(), now).await;
+    (
+        StatusCode::CREATED,
+        [(header::SET_COOKIE, set_cookie(&token, info.secure))],
+        Json(Me { username }),
+    )
+        .into_response()
+}
+
+/// Handler for `POST /api/login`
+pub async fn login(
+    State(store): State<Arc<AccountStore>>,
+    Extension(info): Extension<ClientInfo>,
+    Json(credentials): Json<Credentials>,
+) -> Response {
+    let key = credentials.username.trim().to_lowercase();
+    let mut keys = vec![format!("user:{key}")];
+    if let Some(ip) = info.ip {
+        keys.push(format!("ip:{ip}"));
+    }
+    let now = util::now_secs();
+    if store.is_throttled(&keys, MAX_LOGIN_FAILURES, now).await {
+        return StatusCode::TOO_MANY_REQUESTS.into_response();
+    }
+    // No password this long was ever registered, don't spend time hashing it
+    if credentials.password.chars().count() > MAX_PASSWORD_LENGTH {
+        store.record_attempt(&keys, now).await;
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let account = store.accounts.read().await.accounts.get(&key).cloned();
+    let password = credentials.password;
+    let hash = account.as_ref().map_or_else(
+        || DUMMY_HASH.clone(),
+        |account| account.password_hash.clone(),
+    );
+    let valid = tokio::task::spawn_blocking(move || verify_password(&password, &hash))
+        .await
+        .unwrap();
+    let Some(account) = account.filter(|_| valid) else {
+        warn!("Failed login of {} from {:?}", key, info.ip);
+        store.record_attempt(&keys, now).await;
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    let token = store.login(account.username.clone(), now).await;
+    (
+        [(header::SET_COOKIE, set_cookie(&token, info.secure))],
+        Json(Me {
+            username: account.username,
+        }),
+    )
+        .into_response()
+}
+
+/// Handler for `POST /api/logout`, ends the session of the cookie
+pub async fn logout(
+    State(store): State<Arc<AccountStore>>,
+    Extension(info): Extension<ClientInfo>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(token) = session_cookie(&headers) {
+        store.sessions.write().await.remove(token);
+    }
+    (
+        StatusCode::NO_CONTENT,
+        [(header::SET_COOKIE, set_cookie("", info.secure))],
+    )
+        .into_response()
+}
+
+/// Handler for `GET /api/me`, the user of the session cookie
+pub async fn me(State(store): State<Arc<AccountStore>>, headers: HeaderMap) -> Response {
+    match store.session_user(&headers).await {
+        Some(username) => Json(Me { username }).into_response(),
+        None => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
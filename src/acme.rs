@@ -0,0 +1,528 @@
+use aws_lc_rs::{
+    digest::{digest, SHA256},
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair as _, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    body::Bytes,
+    header::{self, HeaderMap},
+    Method, Request, Uri,
+};
+use hyper_util::rt::TokioIo;
+use rcgen::{CertificateParams, KeyPair};
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer, ServerName},
+    ClientConfig, RootCertStore,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{collections::HashMap, fmt, path::PathBuf, sync::Arc, time::Duration};
+use tokio::{fs, io::AsyncWriteExt, net::TcpStream, sync::RwLock, time};
+use tokio_rustls::TlsConnector;
+use tracing::{error, info, warn};
+
+use crate::util;
+
+/// Directory of the Let's Encrypt production environment
+pub const LETS_ENCRYPT: &str = "https://acme-v02.api.letsencrypt.org/directory";
+
+/// Time before its expiry at which a certificate is renewed,
+/// Let's Encrypt issues them for 90 days
+pub const RENEW_BEFORE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Delay between renewal checks
+const CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+/// Delay before retrying a failed order
+const RETRY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Pending http-01 challenges, mapping tokens to key authorizations
+pub type Challenges = Arc<RwLock<HashMap<String, String>>>;
+
+/// Configuration of the acme mode
+#[derive(Debug, Clone)]
+pub struct AcmeConfig {
+    /// Domain the certificate is issued for
+    pub domain: String,
+    /// Contact email of the account
+    pub email: Option<String>,
+    /// Directory url of the acme server
+    pub directory: String,
+    /// Directory where the account key and certificates are stored
+    pub cache: PathBuf,
+    /// Additional trusted root of the acme server, e.g. the Pebble test CA
+    pub ca_root: Option<PathBuf>,
+}
+
+impl AcmeConfig {
+    fn cert_path(&self) -> PathBuf {
+        self.cache.join("cert.pem")
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.cache.join("key.pem")
+    }
+
+    fn account_path(&self) -> PathBuf {
+        self.cache.join("account.der")
+    }
+}
+
+/// Builds a router answering the http-01 challenges, merged into the port 80 listener
+pub fn challenge_app(challenges: Challenges) -> Router {
+    Router::new()
+        .route("/.well-known/acme-challenge/{token}", get(challenge))
+        .with_state(challenges)
+}
+
+/// Handler for `GET /.well-known/acme-challenge/{token}`
+async fn challenge(State(challenges): State<Challenges>, Path(token): Path<String>) -> Response {
+    match challenges.read().await.get(&token) {
+        Some(key_authorization) => key_authorization.clone().into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Loads the cached certificate or, if there is none yet, a temporary self-signed one
+pub async fn initial_config(config: &AcmeConfig) -> RustlsConfig {
+    if let Ok(tls) = RustlsConfig::from_pem_file(config.cert_path(), config.key_path()).await {
+        info!("Loaded cached certificate for {}", config.domain);
+        return tls;
+    }
+    info!(
+        "Using a self-signed certificate for {} until one is issued",
+        config.domain
+    );
+    let cert = rcgen::generate_simple_self_signed(vec![config.domain.clone()]).unwrap();
+    RustlsConfig::from_pem(
+        cert.cert.pem().into_bytes(),
+        cert.key_pair.serialize_pem().into_bytes(),
+    )
+    .await
+    .unwrap()
+}
+
+/// Spawns a task which obtains a certificate and renews it before it expires,
+/// swapping it into `tls` in place
+pub fn spawn_acme(config: AcmeConfig, tls: RustlsConfig, challenges: Challenges) {
+    tokio::spawn(async move {
+        if let Err(e) = fs::create_dir_all(&config.cache).await {
+            error!("Failed to create acme cache {:?}: {}", config.cache, e);
+            return;
+        }
+        loop {
+            let renew = match fs::read(config.cert_path()).await {
+                Ok(cert) => needs_renewal(&cert, util::now_secs()),
+                Err(_) => true,
+            };
+            if !renew {
+                time::sleep(CHECK_INTERVAL).await;
+                continue;
+            }
+
+            info!("Ordering certificate for {}", config.domain);
+            match order(&config, &challenges).await {
+                Ok((cert, key)) => match tls.reload_from_pem(cert, key).await {
+                    Ok(()) => info!("Installed new certificate for {}", config.domain),
+                    Err(e) => error!("Issued certificate is invalid: {}", e),
+                },
+                Err(e) => {
+                    error!("Ordering certificate failed: {}", e);
+                    time::sleep(RETRY_INTERVAL).await;
+                }
+            }
+        }
+    });
+}
+
+/// Returns the expiry (`notAfter`) of the first certificate in a pem chain in unix seconds
+pub fn expires_at(cert_pem: &[u8]) -> Option<u64> {
+    let der = CertificateDer::from_pem_slice(cert_pem).ok()?;
+    let (_, cert) = x509_parser::parse_x509_certificate(&der).ok()?;
+    u64::try_from(cert.validity().not_after.timestamp()).ok()
+}
+
+/// Whether a certificate expires within [`RENEW_BEFORE`] or cannot be parsed
+pub fn needs_renewal(cert_pem: &[u8], now: u64) -> bool {
+    expires_at(cert_pem).is_none_or(|expiry| expiry.saturating_sub(now) < RENEW_BEFORE.as_secs())
+}
+
+/// Errors of the acme client
+#[derive(Debug)]
+pub struct AcmeError(String);
+
+impl AcmeError {
+    fn new(msg: impl Into<String>) -> Self {
+        Self(msg.into())
+    }
+}
+
+impl fmt::Display for AcmeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<E: std::error::Error> From<E> for AcmeError {
+    fn from(e: E) -> Self {
+        Self(e.to_string())
+    }
+}
+
+type Result<T> = std::result::Result<T, AcmeError>;
+
+/// Orders a certificate via http-01 and stores it in the cache, returns the pem cert and key
+async fn order(config: &AcmeConfig, challenges: &Challenges) -> Result<(Vec<u8>, Vec<u8>)> {
+    let http = Http::new(config)?;
+    let mut account = Account::login(http, config).await?;
+
+    let (order_url, order) = account
+        .post(
+            &account.directory.new_order.clone(),
+            Some(json!({ "identifiers": [{ "type": "dns", "value": config.domain }] })),
+        )
+        .await?;
+    let order_url = order_url.ok_or_else(|| AcmeError::new("order without location"))?;
+    let order: Order = serde_json::from_slice(&order)?;
+
+    for authorization in &order.authorizations {
+        account.authorize(authorization, challenges).await?;
+    }
+
+    let key = KeyPair::generate()?;
+    let csr = CertificateParams::new(vec![config.domain.clone()])?.serialize_request(&key)?;
+    account
+        .post(
+            &order.finalize,
+            Some(json!({ "csr": URL_SAFE_NO_PAD.encode(csr.der()) })),
+        )
+        .await?;
+
+    let order: Order = account
+        .poll(&order_url, |order: &Order| match order.status.as_str() {
+            "valid" => Some(Ok(())),
+            "invalid" => Some(Err(AcmeError::new("order became invalid"))),
+            _ => None,
+        })
+        .await?;
+    let certificate = order
+        .certificate
+        .ok_or_else(|| AcmeError::new("valid order without certificate"))?;
+    let (_, cert) = account.post(&certificate, None).await?;
+
+    let key = key.serialize_pem().into_bytes();
+    write_atomic(&config.key_path(), &key, 0o600).await?;
+    write_atomic(&config.cert_path(), &cert, 0o644).await?;
+    Ok((cert.to_vec(), key))
+}
+
+/// Writes a file with the given unix permissions through a temporary file,
+/// so the reload watcher never sees a partial write
+async fn write_atomic(path: &std::path::Path, contents: &[u8], mode: u32) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    // A leftover could have other permissions
+    let _ = fs::remove_file(&tmp).await;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(mode);
+    #[cfg(not(unix))]
+    let _ = mode;
+    let mut file = options.open(&tmp).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await?;
+    fs::rename(&tmp, path).await
+}
+
+/// Endpoints of the acme server
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+    new_nonce: String,
+    new_account: String,
+    new_order: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Order {
+    status: String,
+    #[serde(default)]
+    authorizations: Vec<String>,
+    finalize: String,
+    certificate: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Authorization {
+    status: String,
+    challenges: Vec<Challenge>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Challenge {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    token: String,
+}
+
+/// A registered acme account signing its requests
+struct Account {
+    http: Http,
+    key: EcdsaKeyPair,
+    directory: Directory,
+    /// Account url used as key id once registered
+    kid: Option<String>,
+    nonce: Option<String>,
+}
+
+impl Account {
+    /// Registers or looks up the account of the cached key
+    async fn login(http: Http, config: &AcmeConfig) -> Result<Self> {
+        let pkcs8 = match fs::read(config.account_path()).await {
+            Ok(pkcs8) => pkcs8,
+            Err(_) => {
+                let pkcs8 = EcdsaKeyPair::generate_pkcs8(
+                    &ECDSA_P256_SHA256_FIXED_SIGNING,
+                    &SystemRandom::new(),
+                )?;
+                write_atomic(&config.account_path(), pkcs8.as_ref(), 0o600).await?;
+                pkcs8.as_ref().to_vec()
+            }
+        };
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &pkcs8)?;
+
+        let (_, _, body) = http.request(Method::GET, &config.directory, None).await?;
+        let directory: Directory = serde_json::from_slice(&body)?;
+
+        let mut account = Self {
+            http,
+            key,
+            directory,
+            kid: None,
+            nonce: None,
+        };
+        let mut payload = json!({ "termsOfServiceAgreed": true });
+        if let Some(email) = &config.email {
+            payload["contact"] = json!([format!("mailto:{email}")]);
+        }
+        let (kid, _) = account
+            .post(&account.directory.new_account.clone(), Some(payload))
+            .await?;
+        account.kid = Some(kid.ok_or_else(|| AcmeError::new("account without location"))?);
+        Ok(account)
+    }
+
+    /// Completes the http-01 challenge of an authorization
+    async fn authorize(&mut self, url: &str, challenges: &Challenges) -> Result<()> {
+        let (_, body) = self.post(url, None).await?;
+        let authorization: Authorization = serde_json::from_slice(&body)?;
+        if authorization.status == "valid" {
+            return Ok(());
+        }
+        let challenge = authorization
+            .challenges
+            .into_iter()
+            .find(|c| c.kind == "http-01")
+            .ok_or_else(|| AcmeError::new("no http-01 challenge offered"))?;
+
+        let key_authorization = format!("{}.{}", challenge.token, self.thumbprint());
+        challenges
+            .write()
+            .await
+            .insert(challenge.token.clone(), key_authorization);
+
+        let result = async {
+            self.post(&challenge.url, Some(json!({}))).await?;
+            self.poll(url, |authorization: &Authorization| {
+                match authorization.status.as_str() {
+                    "valid" => Some(Ok(())),
+                    "pending" | "processing" => None,
+                    status => Some(Err(AcmeError::new(format!("authorization {status}")))),
+                }
+            })
+            .await
+        }
+        .await;
+
+        challenges.write().await.remove(&challenge.token);
+        result.map(|_| ())
+    }
+
+    /// Fetches `url` until `done` returns a result
+    async fn poll<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        url: &str,
+        done: impl Fn(&T) -> Option<Result<()>>,
+    ) -> Result<T> {
+        for _ in 0..30 {
+            let (_, body) = self.post(url, None).await?;
+            let value: T = serde_json::from_slice(&body)?;
+            if let Some(result) = done(&value) {
+                return result.map(|()| value);
+            }
+            time::sleep(Duration::from_secs(2)).await;
+        }
+        Err(AcmeError::new(format!("timed out polling {url}")))
+    }
+
+    /// Sends a signed request, `None` as payload is a POST-as-GET.
+    /// Returns the location header and the body.
+    async fn post(&mut self, url: &str, payload: Option<Value>) -> Result<(Option<String>, Bytes)> {
+        // A bad nonce is retried once with the fresh nonce of the error response
+        let mut retried = false;
+        loop {
+            let nonce = match self.nonce.take() {
+                Some(nonce) => nonce,
+                None => {
+                    let new_nonce = self.directory.new_nonce.clone();
+                    let (_, headers, _) = self.http.request(Method::HEAD, &new_nonce, None).await?;
+                    header_value(&headers, "replay-nonce")
+                        .ok_or_else(|| AcmeError::new("no nonce received"))?
+                }
+            };
+
+            let body = self.sign(url, &nonce, payload.as_ref())?;
+            let (status, headers, response) =
+                self.http.request(Method::POST, url, Some(body)).await?;
+            self.nonce = header_value(&headers, "replay-nonce");
+
+            if status.is_success() {
+                return Ok((header_value(&headers, "location"), response));
+            }
+            let problem: Value = serde_json::from_slice(&response).unwrap_or_default();
+            if !retried && problem["type"] == "urn:ietf:params:acme:error:badNonce" {
+                warn!("Retrying acme request with a fresh nonce");
+                retried = true;
+                continue;
+            }
+            return Err(AcmeError::new(format!(
+                "{url} returned {status}: {problem}"
+            )));
+        }
+    }
+
+    /// Creates the flattened jws of a request
+    fn sign(&self, url: &str, nonce: &str, payload: Option<&Value>) -> Result<String> {
+        let mut protected = json!({ "alg": "ES256", "nonce": nonce, "url": url });
+        match &self.kid {
+            Some(kid) => protected["kid"] = json!(kid),
+            None => protected["jwk"] = self.jwk(),
+        }
+        let protected = URL_SAFE_NO_PAD.encode(protected.to_string());
+        let payload = payload.map_or(String::new(), |p| URL_SAFE_NO_PAD.encode(p.to_string()));
+
+        let signature = self.key.sign(
+            &SystemRandom::new(),
+            format!("{protected}.{payload}").as_bytes(),
+        )?;
+        Ok(json!({
+            "protected": protected,
+            "payload": payload,
+            "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+        })
+        .to_string())
+    }
+
+    /// Public key of the account as jwk, with the members in lexicographic order
+    fn jwk(&self) -> Value {
+        // Uncompressed point: 0x04 || x || y
+        let point = self.key.public_key().as_ref();
+        json!({
+            "crv": "P-256",
+            "kty": "EC",
+            "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+            "y": URL_SAFE_NO_PAD.encode(&point[33..65]),
+        })
+    }
+
+    /// Jwk thumbprint of the account key (RFC 7638)
+    fn thumbprint(&self) -> String {
+        // serde_json keeps objects sorted, so this is the canonical form
+        URL_SAFE_NO_PAD.encode(digest(&SHA256, self.jwk().to_string().as_bytes()))
+    }
+}
+
+/// Returns a header as string
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Minimal https client for the acme server
+struct Http {
+    tls: TlsConnector,
+}
+
+impl Http {
+    fn new(config: &AcmeConfig) -> Result<Self> {
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        if let Some(ca_root) = &config.ca_root {
+            for cert in CertificateDer::pem_file_iter(ca_root)? {
+                roots.add(cert?)?;
+            }
+        }
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let mut client = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        client.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Self {
+            tls: TlsConnector::from(Arc::new(client)),
+        })
+    }
+
+    /// Sends a request with an optional jose body over a new connection
+    async fn request(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<String>,
+    ) -> Result<(StatusCode, HeaderMap, Bytes)> {
+        let uri: Uri = url.parse()?;
+        let authority = uri
+            .authority()
+            .ok_or_else(|| AcmeError::new(format!("no host in {url}")))?
+            .clone();
+        let host = authority.host().to_string();
+        let port = authority.port_u16().unwrap_or(443);
+
+        let tcp = TcpStream::connect((host.as_str(), port)).await?;
+        let stream = self
+            .tls
+            .connect(ServerName::try_from(host.clone())?, tcp)
+            .await?;
+        let (mut sender, connection) =
+            hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(connection);
+
+        let path = uri.path_and_query().map_or("/", |p| p.as_str());
+        let mut request = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(header::HOST, authority.as_str())
+            .header(header::USER_AGENT, util::PKG_NAME);
+        if body.is_some() {
+            request = request.header(header::CONTENT_TYPE, "application/jose+json");
+        }
+        let request = request.body(Full::new(Bytes::from(body.unwrap_or_default())))?;
+
+        let response = sender.send_request(request).await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.into_body().collect().await?.to_bytes();
+        Ok((status, headers, body))
+    }
+}
@@ -0,0 +1,65 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::{net::IpAddr, sync::Arc};
+
+use crate::{bot::BotSummary, ws::WebSocketState};
+
+/// Middleware guarding the admin routes with the `Authorization: Bearer <token>` header,
+/// the routes do not exist without a configured token
+pub async fn require_token(
+    State(token): State<Option<Arc<str>>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(token) = token else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let authorized = provided.is_some_and(|provided| {
+        aws_lc_rs::constant_time::verify_slices_are_equal(provided.as_bytes(), token.as_bytes())
+            .is_ok()
+    });
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(req).await
+}
+
+/// A connected client suspected of automation
+#[derive(Debug, Serialize)]
+pub struct FlaggedClient {
+    pub id: usize,
+    pub nickname: Option<String>,
+    pub identity: Option<String>,
+    pub ip: Option<IpAddr>,
+    #[serde(flatten)]
+    pub stats: BotSummary,
+}
+
+/// Handler for `GET /api/admin/flagged`, lists the connected clients scored as bots
+pub async fn flagged(State(state): State<Arc<WebSocketState>>) -> impl IntoResponse {
+    let clients = state.clients.read().await;
+    let mut flagged: Vec<_> = clients
+        .iter()
+        .map(|(id, client)| FlaggedClient {
+            id: *id,
+            nickname: client.nickname.clone(),
+            identity: client.identity.clone(),
+            ip: client.ip,
+            stats: client.bot.summary(),
+        })
+        .filter(|client| client.stats.score >= state.bot.threshold)
+        .collect();
+    flagged.sort_by_key(|client| client.id);
+    Json(flagged)
+}
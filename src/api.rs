@@ -0,0 +1,39 @@
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::ws::WebSocketState;
+
+/// Handler for `GET /api/seasons`, lists the completed seasons from fastest to slowest
+pub async fn seasons(State(state): State<Arc<WebSocketState>>) -> impl IntoResponse {
+    Json(state.hall_of_fame.read().await.leaderboard())
+}
+
+/// Handler for `GET /api/polls`, lists the finished polls of the current season, oldest first
+pub async fn polls(State(state): State<Arc<WebSocketState>>) -> impl IntoResponse {
+    Json(state.engine.read().await.counter.poll_history.clone())
+}
+
+/// Handler for `GET /api/upgrades`, lists the upgrades of the catalogue with their labels
+pub async fn upgrades(State(state): State<Arc<WebSocketState>>) -> impl IntoResponse {
+    Json(state.engine.read().await.config.upgrades.upgrades.clone())
+}
+
+/// Query parameters of `GET /api/leaderboard`
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardParams {
+    pub limit: Option<usize>,
+}
+
+/// Handler for `GET /api/leaderboard`, lists the top contributors without their identities
+pub async fn leaderboard(
+    Query(params): Query<LeaderboardParams>,
+    State(state): State<Arc<WebSocketState>>,
+) -> impl IntoResponse {
+    let limit = params.limit.unwrap_or(10).min(100);
+    Json(state.contributions.read().await.leaderboard(limit))
+}
@@ -0,0 +1,221 @@
+use axum::{
+    body::Body,
+    error_handling::HandleErrorLayer,
+    extract::{Path, State},
+    http::{header, HeaderValue, Request, StatusCode, Uri},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{any, delete, get, post},
+    Extension, Router,
+};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tower::{BoxError, ServiceBuilder};
+use tower_http::{compression::CompressionLayer, trace::TraceLayer};
+use tracing::error;
+
+use crate::{
+    account::{self, AccountStore},
+    admin, api,
+    challenge::{self, Challenger},
+    net::{self, ProxyConfig},
+    schedule,
+    security::{self, SecurityConfig},
+    ws::{ws_handler, JoinConfig, WebSocketState},
+};
+
+/// Configuration of the http routes
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    /// Path to the view folder, unused if the frontend is embedded
+    pub view: PathBuf,
+    pub proxy: ProxyConfig,
+    pub security: SecurityConfig,
+    /// Bearer token of the admin api, disabled if unset
+    pub admin_token: Option<Arc<str>>,
+    /// Proof-of-work required to join, disabled if unset
+    pub challenge: Option<Arc<Challenger>>,
+    /// Registered users and their sessions
+    pub accounts: Arc<AccountStore>,
+}
+
+/// Builds the router with all routes and middleware, independent of how it is served
+pub fn build_app(config: &AppConfig, ws_state: Arc<WebSocketState>) -> Router {
+    Router::new()
+        .route("/ws", any(ws_handler).with_state(ws_state.clone()))
+        .route(
+            "/api/seasons",
+            get(api::seasons).with_state(ws_state.clone()),
+        )
+        .route("/api/polls", get(api::polls).with_state(ws_state.clone()))
+        .route(
+            "/api/upgrades",
+            get(api::upgrades).with_state(ws_state.clone()),
+        )
+        .route(
+            "/api/leaderboard",
+            get(api::leaderboard).with_state(ws_state.clone()),
+        )
+        .route(
+            "/api/register",
+            post(account::register).with_state(config.accounts.clone()),
+        )
+        .route(
+            "/api/login",
+            post(account::login).with_state(config.accounts.clone()),
+        )
+        .route(
+            "/api/logout",
+            post(account::logout).with_state(config.accounts.clone()),
+        )
+        .route(
+            "/api/me",
+            get(account::me).with_state(config.accounts.clone()),
+        )
+        .route(
+            "/api/challenge",
+            get(challenge::challenge).with_state((config.challenge.clone(), ws_state.clone())),
+        )
+        .route(
+            "/api/admin/flagged",
+            get(admin::flagged)
+                .with_state(ws_state.clone())
+                .route_layer(middleware::from_fn_with_state(
+                    config.admin_token.clone(),
+                    admin::require_token,
+                )),
+        )
+        .route(
+            "/api/admin/events",
+            get(schedule::list)
+                .post(schedule::schedule)
+                .with_state(ws_state.schedule.clone())
+                .route_layer(middleware::from_fn_with_state(
+                    config.admin_token.clone(),
+                    admin::require_token,
+                )),
+        )
+        .route(
+            "/api/admin/events/{id}",
+            delete(schedule::cancel)
+                .with_state(ws_state.schedule.clone())
+                .route_layer(middleware::from_fn_with_state(
+                    config.admin_token.clone(),
+                    admin::require_token,
+                )),
+        )
+        .route("/", get(static_index).with_state(config.view.clone()))
+        .route(
+            "/{*file}",
+            get(static_assets).with_state(config.view.clone()),
+        )
+        .layer(Extension(JoinConfig {
+            origins: config.security.allowed_origins.clone(),
+            challenger: config.challenge.clone(),
+            accounts: config.accounts.clone(),
+        }))
+        .layer(middleware::from_fn_with_state(
+            config.security.clone(),
+            security::security_headers,
+        ))
+        .layer(middleware::from_fn_with_state(
+            config.proxy,
+            net::client_info,
+        ))
+        .layer(
+            ServiceBuilder::new()
+                .layer(config.security.allowed_origins.cors())
+                .layer(CompressionLayer::new())
+                .layer(HandleErrorLayer::new(|error: BoxError| async move {
+                    if error.is::<tower::timeout::error::Elapsed>() {
+                        Ok(StatusCode::REQUEST_TIMEOUT)
+                    } else {
+                        error!("Internal server error: {error}");
+                        Err(StatusCode::INTERNAL_SERVER_ERROR)
+                    }
+                }))
+                .timeout(Duration::from_secs(10))
+                .layer(TraceLayer::new_for_http())
+                .into_inner(),
+        )
+}
+
+async fn static_index(State(path): State<PathBuf>, mut req: Request<Body>) -> Response {
+    *req.uri_mut() = Uri::from_static("/index.html");
+    serve_static(&path, "index.html", req).await
+}
+
+async fn static_assets(
+    State(path): State<PathBuf>,
+    Path(file): Path<String>,
+    mut req: Request<Body>,
+) -> Response {
+    if file.contains('.') {
+        return serve_static(&path, &file, req).await;
+    }
+    // Prerendered pages are stored as `<route>.html`
+    match format!("{}.html", req.uri().path()).parse() {
+        Ok(uri) => *req.uri_mut() = uri,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    }
+    serve_static(&path, &format!("{file}.html"), req).await
+}
+
+/// Serves a file of the frontend with its cache policy, falling back to the built `404.html`
+async fn serve_static(view: &std::path::Path, file: &str, req: Request<Body>) -> Response {
+    let not_found = req.headers().clone();
+    let mut response = load_static(view, file, req).await;
+    if response.status() == StatusCode::NOT_FOUND {
+        let mut req = Request::new(Body::empty());
+        *req.uri_mut() = Uri::from_static("/404.html");
+        *req.headers_mut() = not_found;
+        req.headers_mut().remove(header::IF_NONE_MATCH);
+        response = load_static(view, "404.html", req).await;
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    } else if response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, cache_control(file));
+    }
+    response
+}
+
+/// Hashed build output never changes, html must be revalidated to pick up new builds
+fn cache_control(file: &str) -> HeaderValue {
+    if file.starts_with("_app/immutable/") {
+        HeaderValue::from_static("public, max-age=31536000, immutable")
+    } else if file.ends_with(".html") {
+        HeaderValue::from_static("no-cache")
+    } else {
+        HeaderValue::from_static("public, max-age=86400")
+    }
+}
+
+/// Loads a file of the frontend from memory
+#[cfg(feature = "embed")]
+async fn load_static(_view: &std::path::Path, file: &str, req: Request<Body>) -> Response {
+    match crate::assets::get(file) {
+        Some(asset) => asset.serve(req.headers()),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Loads a file of the frontend from the view directory, preferring precompressed siblings
+#[cfg(not(feature = "embed"))]
+async fn load_static(view: &std::path::Path, _file: &str, req: Request<Body>) -> Response {
+    use tower::ServiceExt;
+    use tower_http::services::ServeDir;
+
+    let result = ServeDir::new(view)
+        .precompressed_br()
+        .precompressed_gzip()
+        .oneshot(req)
+        .await;
+    // Io errors are already turned into error responses
+    match result {
+        Ok(response) => response.into_response(),
+        Err(infallible) => match infallible {},
+    }
+}
@@ -0,0 +1,85 @@
+//! The built frontend, embedded at compile time by `build.rs` with the `embed` feature.
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// A file of the built frontend with its precompressed variants
+pub struct Asset {
+    /// Path relative to the build directory
+    pub path: &'static str,
+    pub content_type: &'static str,
+    /// Quoted content hash
+    pub etag: &'static str,
+    pub raw: &'static [u8],
+    pub gzip: Option<&'static [u8]>,
+    pub brotli: Option<&'static [u8]>,
+}
+
+/// All embedded files, sorted by path
+pub static ASSETS: &[Asset] = include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+/// Returns the embedded file at `path`
+pub fn get(path: &str) -> Option<&'static Asset> {
+    ASSETS
+        .binary_search_by(|asset| asset.path.cmp(path))
+        .ok()
+        .map(|i| &ASSETS[i])
+}
+
+impl Asset {
+    /// Responds with the best encoding accepted by the client or `304` if it is cached
+    pub fn serve(&self, headers: &HeaderMap) -> Response {
+        let etag = HeaderValue::from_static(self.etag);
+        if headers
+            .get(header::IF_NONE_MATCH)
+            .is_some_and(|tags| matches_etag(tags, self.etag))
+        {
+            return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+        }
+
+        let (encoding, body) = match (accepts(headers, "br"), accepts(headers, "gzip")) {
+            (true, _) if self.brotli.is_some() => (Some("br"), self.brotli.unwrap()),
+            (_, true) if self.gzip.is_some() => (Some("gzip"), self.gzip.unwrap()),
+            _ => (None, self.raw),
+        };
+
+        let mut response = Response::new(Body::from(body));
+        let headers = response.headers_mut();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(self.content_type),
+        );
+        headers.insert(header::ETAG, etag);
+        headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+        if let Some(encoding) = encoding {
+            headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        }
+        response
+    }
+}
+
+/// Whether `If-None-Match` contains the etag
+fn matches_etag(tags: &HeaderValue, etag: &str) -> bool {
+    tags.to_str().is_ok_and(|tags| {
+        tags.split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == etag)
+    })
+}
+
+/// Whether `Accept-Encoding` allows an encoding, ignoring quality values other than `q=0`
+fn accepts(headers: &HeaderMap, encoding: &str) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| {
+            let mut parts = value.split(';').map(str::trim);
+            parts.next().is_some_and(|name| name == encoding)
+                && !parts.any(|param| param == "q=0" || param == "q=0.0")
+        })
+}
@@ -0,0 +1,416 @@
+//! Headless load-testing client which opens many websocket connections to a running server.
+
+use clap::Parser;
+use futures::{SinkExt, StreamExt};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::CryptoProvider,
+    pki_types::{pem::PemObject, CertificateDer, ServerName, UnixTime},
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    time,
+};
+use tokio_rustls::TlsConnector;
+use tokio_tungstenite::{client_async, tungstenite::Message};
+
+/// Command-line arguments of the load test
+#[derive(Parser)]
+#[command(name = "loadtest")]
+struct Args {
+    /// Websocket url of the server
+    #[arg(default_value = "ws://localhost:8080/ws")]
+    url: String,
+
+    /// Number of concurrent connections
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    connections: usize,
+
+    /// Delay between opening two connections in milliseconds
+    #[arg(long, default_value_t = 2)]
+    ramp_ms: u64,
+
+    /// Duration of the measurement once all connections are open, in seconds
+    #[arg(short, long, default_value_t = 30)]
+    duration: u64,
+
+    /// Share of the connections which decrement instead of increment
+    #[arg(long, default_value_t = 0.2)]
+    decrement_ratio: f64,
+
+    /// `action` commands per second and connection
+    #[arg(long, default_value_t = 4.0)]
+    action_rate: f64,
+
+    /// Side switches (`increment`/`decrement`) per second and connection
+    #[arg(long, default_value_t = 0.05)]
+    switch_rate: f64,
+
+    /// Poll votes (`vote base`/`vote exponent`) per second and connection
+    #[arg(long, default_value_t = 0.1)]
+    vote_rate: f64,
+
+    /// Trust the certificates in this pem file for `wss://` urls
+    #[arg(long)]
+    ca: Option<PathBuf>,
+
+    /// Skip certificate verification for `wss://` urls
+    #[arg(long)]
+    insecure: bool,
+}
+
+/// Measurements shared by all connections
+#[derive(Default)]
+struct Stats {
+    connected: AtomicUsize,
+    failed: AtomicUsize,
+    disconnected: AtomicUsize,
+    commands: AtomicUsize,
+    received: AtomicUsize,
+    /// Whether the measurement window is open
+    measuring: AtomicBool,
+    connect_times: Mutex<Vec<Duration>>,
+    /// First arrival and number of receivers of each broadcast seen while measuring
+    broadcasts: Mutex<HashMap<u64, (Instant, usize)>>,
+    /// Delay of each arrival after the first arrival of the same broadcast
+    spreads: Mutex<Vec<Duration>>,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Arc::new(Args::parse());
+    let stats = Arc::new(Stats::default());
+
+    let tls = if args.url.starts_with("wss://") {
+        Some(tls_connector(&args))
+    } else {
+        None
+    };
+
+    println!(
+        "Opening {} connections to {}...",
+        args.connections, args.url
+    );
+    let mut tasks = Vec::with_capacity(args.connections);
+    for i in 0..args.connections {
+        tasks.push(tokio::spawn(run_client(
+            i,
+            args.clone(),
+            tls.clone(),
+            stats.clone(),
+        )));
+        time::sleep(Duration::from_millis(args.ramp_ms)).await;
+    }
+
+    // Let the last connections settle before measuring
+    time::sleep(Duration::from_secs(1)).await;
+    let alive =
+        stats.connected.load(Ordering::Relaxed) - stats.disconnected.load(Ordering::Relaxed);
+    println!(
+        "{alive} connections open, measuring for {}s...",
+        args.duration
+    );
+
+    stats.measuring.store(true, Ordering::Relaxed);
+    time::sleep(Duration::from_secs(args.duration)).await;
+    stats.measuring.store(false, Ordering::Relaxed);
+
+    for task in tasks {
+        task.abort();
+    }
+
+    print_summary(&args, &stats, alive);
+}
+
+/// Runs a single connection until it is aborted or closed
+async fn run_client(index: usize, args: Arc<Args>, tls: Option<TlsConnector>, stats: Arc<Stats>) {
+    let start = Instant::now();
+    let stream = match connect(&args.url, tls).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            if stats.failed.fetch_add(1, Ordering::Relaxed) == 0 {
+                eprintln!("Connection failed: {e}");
+            }
+            return;
+        }
+    };
+    stats.connect_times.lock().unwrap().push(start.elapsed());
+    stats.connected.fetch_add(1, Ordering::Relaxed);
+
+    let (mut sender, mut receiver) = stream;
+    let mut rng = StdRng::seed_from_u64(index as u64);
+    let mut decrementing = rng.gen_bool(args.decrement_ratio.clamp(0.0, 1.0));
+    let side = |decrementing| {
+        if decrementing {
+            "decrement"
+        } else {
+            "increment"
+        }
+    };
+    if sender
+        .send(Message::text(side(decrementing)))
+        .await
+        .is_err()
+    {
+        stats.disconnected.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+
+    // Commands are sampled every 50ms with the configured rates
+    let mut interval = time::interval(Duration::from_millis(50));
+    let slot = 0.05;
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let mut commands = vec![];
+                if rng.gen_bool((args.action_rate * slot).clamp(0.0, 1.0)) {
+                    commands.push("action");
+                }
+                if rng.gen_bool((args.switch_rate * slot).clamp(0.0, 1.0)) {
+                    decrementing = !decrementing;
+                    commands.push(side(decrementing));
+                }
+                if rng.gen_bool((args.vote_rate * slot).clamp(0.0, 1.0)) {
+                    commands.push(if rng.gen_bool(0.5) { "vote base" } else { "vote exponent" });
+                }
+                for command in commands {
+                    if sender.send(Message::text(command)).await.is_err() {
+                        stats.disconnected.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                    stats.commands.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            message = receiver.next() => {
+                let Some(Ok(Message::Text(text))) = message else {
+                    if !matches!(message, Some(Ok(_))) {
+                        stats.disconnected.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                    continue;
+                };
+                let now = Instant::now();
+                stats.received.fetch_add(1, Ordering::Relaxed);
+                // Only counter updates are unique per broadcast, events may repeat
+                if stats.measuring.load(Ordering::Relaxed) && !text.starts_with("{\"event\"") {
+                    record(&stats, text.as_str(), now);
+                }
+            }
+        }
+    }
+}
+
+type Sink =
+    futures::stream::SplitSink<tokio_tungstenite::WebSocketStream<Box<dyn Stream>>, Message>;
+type Source = futures::stream::SplitStream<tokio_tungstenite::WebSocketStream<Box<dyn Stream>>>;
+
+/// A plain or tls connection
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// Opens the websocket connection
+async fn connect(url: &str, tls: Option<TlsConnector>) -> Result<(Sink, Source), String> {
+    let authority = url
+        .split("://")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .ok_or_else(|| format!("invalid url {url:?}"))?;
+    let host = authority
+        .rsplit_once(':')
+        .map_or(authority, |(host, _)| host)
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else if tls.is_some() {
+        format!("{authority}:443")
+    } else {
+        format!("{authority}:80")
+    };
+
+    let tcp = TcpStream::connect(&address)
+        .await
+        .map_err(|e| e.to_string())?;
+    tcp.set_nodelay(true).map_err(|e| e.to_string())?;
+    let stream: Box<dyn Stream> = match tls {
+        Some(tls) => {
+            let name = ServerName::try_from(host).map_err(|e| e.to_string())?;
+            Box::new(tls.connect(name, tcp).await.map_err(|e| e.to_string())?)
+        }
+        None => Box::new(tcp),
+    };
+
+    let (stream, _) = client_async(url, stream).await.map_err(|e| e.to_string())?;
+    Ok(stream.split())
+}
+
+/// Records the arrival of a broadcast message
+fn record(stats: &Stats, text: &str, now: Instant) {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    let mut broadcasts = stats.broadcasts.lock().unwrap();
+    let (first, receivers) = broadcasts.entry(hash).or_insert((now, 0));
+    *receivers += 1;
+    let spread = now.saturating_duration_since(*first);
+    drop(broadcasts);
+    stats.spreads.lock().unwrap().push(spread);
+}
+
+/// Creates the tls connector from the `--ca` and `--insecure` options
+fn tls_connector(args: &Args) -> TlsConnector {
+    let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .unwrap();
+    let config = if args.insecure {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerification(provider)))
+            .with_no_client_auth()
+    } else {
+        let Some(ca) = &args.ca else {
+            eprintln!("wss:// urls require --ca <pem> or --insecure");
+            std::process::exit(1);
+        };
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(ca).unwrap() {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        builder.with_root_certificates(roots).with_no_client_auth()
+    };
+    TlsConnector::from(Arc::new(config))
+}
+
+/// Accepts any server certificate, for self-signed local setups
+#[derive(Debug)]
+struct NoVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Prints the results of the load test
+fn print_summary(args: &Args, stats: &Stats, alive: usize) {
+    let connected = stats.connected.load(Ordering::Relaxed);
+    let failed = stats.failed.load(Ordering::Relaxed);
+    let disconnected = stats.disconnected.load(Ordering::Relaxed);
+
+    let mut connect_times = stats.connect_times.lock().unwrap().clone();
+    connect_times.sort();
+    let mut spreads = stats.spreads.lock().unwrap().clone();
+    spreads.sort();
+
+    let broadcasts = stats.broadcasts.lock().unwrap();
+    let expected = broadcasts.len() * alive;
+    let delivered: usize = broadcasts.values().map(|(_, receivers)| receivers).sum();
+    let dropped = expected.saturating_sub(delivered);
+
+    println!();
+    println!("Connections");
+    println!("  opened:        {connected}/{}", args.connections);
+    println!("  failed:        {failed}");
+    println!("  disconnected:  {disconnected}");
+    println!(
+        "  connect time:  p50 {:?}, p99 {:?}, max {:?}",
+        percentile(&connect_times, 0.5),
+        percentile(&connect_times, 0.99),
+        connect_times.last().copied().unwrap_or_default()
+    );
+    println!("Traffic");
+    println!(
+        "  commands sent: {}",
+        stats.commands.load(Ordering::Relaxed)
+    );
+    println!(
+        "  messages recv: {}",
+        stats.received.load(Ordering::Relaxed)
+    );
+    println!(
+        "  broadcasts:    {} in {}s ({:.1}/s)",
+        broadcasts.len(),
+        args.duration,
+        broadcasts.len() as f64 / args.duration.max(1) as f64
+    );
+    println!(
+        "  dropped:       {dropped}/{expected} ({:.2}%)",
+        if expected > 0 {
+            dropped as f64 * 100.0 / expected as f64
+        } else {
+            0.0
+        }
+    );
+    println!("Broadcast latency (after the first receiver)");
+    println!(
+        "  p50 {:?}, p95 {:?}, p99 {:?}, max {:?}",
+        percentile(&spreads, 0.5),
+        percentile(&spreads, 0.95),
+        percentile(&spreads, 0.99),
+        spreads.last().copied().unwrap_or_default()
+    );
+}
+
+/// Returns the given percentile of sorted durations
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let index = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[index]
+}
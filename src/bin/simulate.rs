@@ -0,0 +1,329 @@
+//! Offline simulator which plays the game with synthetic players at full speed.
+
+use clap::{Parser, ValueEnum};
+use num_bigint::BigInt;
+use one_googol::{
+    counter::{Counter, PollOption},
+    engine::{ClientInput, EngineConfig, GameEngine, TickInput},
+    event::Event,
+    util,
+    ws::{CounterState, PollState},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+use std::time::Duration;
+
+/// Command-line arguments of the simulator
+#[derive(Parser)]
+#[command(name = "simulate")]
+struct Args {
+    /// Number of incrementing players
+    #[arg(short = 'n', long, default_value_t = 50)]
+    clients: usize,
+
+    /// Number of decrementing players
+    #[arg(short, long, default_value_t = 0)]
+    saboteurs: usize,
+
+    /// Number of connected players which do not pick a side
+    #[arg(long, default_value_t = 0)]
+    idle: usize,
+
+    /// Average action clicks per second of an active player
+    #[arg(long, default_value_t = 2.0)]
+    click_rate: f64,
+
+    /// How the players vote in upgrade polls
+    #[arg(long, value_enum, default_value_t = Voting::Random)]
+    voting: Voting,
+
+    /// Probability of a player abstaining in a poll, counting towards the quorum
+    #[arg(long, default_value_t = 0.0)]
+    abstain: f64,
+
+    /// Duration of one tick in milliseconds
+    #[arg(long, default_value_t = util::UPDATE_PERIOD_MS)]
+    tick_ms: u64,
+
+    /// Simulated time after which the simulation gives up, in hours
+    #[arg(long, default_value_t = 24.0 * 365.0)]
+    max_hours: f64,
+
+    /// Seed of the random player behaviour
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Output format of the report
+    #[arg(short, long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+}
+
+/// Voting behaviour of the simulated players
+#[derive(Clone, Copy, ValueEnum)]
+enum Voting {
+    /// Everyone votes for the first option, the base in the classic catalogue
+    Base,
+    /// Everyone votes for the last option, the exponent in the classic catalogue
+    Exponent,
+    /// Every player picks a random option per poll
+    Random,
+    /// The players spread evenly over the options
+    Split,
+}
+
+/// Report output format
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+}
+
+/// A point of the digit-length curve, recorded whenever the length changes
+#[derive(Serialize)]
+struct DigitPoint {
+    tick: u64,
+    seconds: f64,
+    digits: usize,
+}
+
+/// An upgrade decided by a poll
+#[derive(Serialize)]
+struct UpgradePoint {
+    tick: u64,
+    seconds: f64,
+    level: usize,
+    choice: Option<String>,
+    base: u32,
+    exponent: u32,
+}
+
+/// Result of a simulation run
+#[derive(Serialize)]
+struct Report {
+    reached: bool,
+    ticks: u64,
+    simulated_seconds: f64,
+    /// Simulated time if reached, else an extrapolation from the last step
+    projected_seconds: Option<f64>,
+    digits: Vec<DigitPoint>,
+    upgrades: Vec<UpgradePoint>,
+}
+
+/// A synthetic player
+struct Player {
+    counter_state: CounterState,
+    /// Expected action clicks per tick
+    clicks_per_tick: f64,
+    /// Index of the fixed vote for the `Split` behaviour
+    split_vote: usize,
+}
+
+fn main() {
+    let args = Args::parse();
+    let tick = Duration::from_millis(args.tick_ms.max(1));
+    let tick_secs = tick.as_secs_f64();
+    let max_ticks = (args.max_hours * 3600.0 / tick_secs) as u64;
+    let mut rng = StdRng::seed_from_u64(args.seed);
+
+    let players = players(&args, tick_secs, &mut rng);
+    let mut votes: Vec<PollState> = vec![PollState::Pending; players.len()];
+
+    let mut engine = GameEngine::new(
+        Counter::new(0),
+        EngineConfig {
+            tick,
+            ..Default::default()
+        },
+    );
+    let one_googol = BigInt::parse_bytes(util::ONE_GOOGOL.as_bytes(), 10).unwrap();
+
+    let mut digits = vec![DigitPoint {
+        tick: 0,
+        seconds: 0.0,
+        digits: 1,
+    }];
+    let mut upgrades = vec![];
+    let mut reached = false;
+    let mut last_value = BigInt::default();
+    let mut ticks = 0;
+
+    while ticks < max_ticks {
+        if let Some(poll) = &engine.counter.poll {
+            if votes.iter().all(|vote| *vote == PollState::Pending) {
+                for (vote, player) in votes.iter_mut().zip(&players) {
+                    *vote = pick_vote(args.voting, args.abstain, player, &poll.options, &mut rng);
+                }
+            }
+        } else {
+            votes.iter_mut().for_each(|vote| *vote = PollState::Pending);
+        }
+
+        let clients = players
+            .iter()
+            .zip(&votes)
+            .map(|(player, vote)| ClientInput {
+                counter_state: player.counter_state.clone(),
+                action_clicks: clicks(player.clicks_per_tick, &mut rng),
+                poll_state: vote.clone(),
+                ..Default::default()
+            })
+            .collect();
+
+        last_value = engine.counter.count.value.clone();
+        let output = engine.tick(&TickInput {
+            now: (ticks as f64 * tick_secs) as u64,
+            clients,
+            ..Default::default()
+        });
+        ticks += 1;
+        let seconds = ticks as f64 * tick_secs;
+
+        let length = engine.counter.count_string().len();
+        if digits.last().is_some_and(|point| point.digits != length) {
+            digits.push(DigitPoint {
+                tick: ticks,
+                seconds,
+                digits: length,
+            });
+        }
+
+        let path = &engine.counter.season.upgrade_path;
+        if path.len() > upgrades.len() {
+            let upgrade = &engine.counter.upgrade;
+            upgrades.push(UpgradePoint {
+                tick: ticks,
+                seconds,
+                level: upgrade.level,
+                choice: upgrade.last_upgrade.clone(),
+                base: upgrade.base,
+                exponent: upgrade.exponent,
+            });
+        }
+
+        if output
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::Completed(_)))
+        {
+            reached = true;
+            break;
+        }
+    }
+
+    let simulated_seconds = ticks as f64 * tick_secs;
+    let projected_seconds = if reached {
+        Some(simulated_seconds)
+    } else {
+        let value = &engine.counter.count.value;
+        let step = value - &last_value;
+        if step > BigInt::default() {
+            let remaining_ticks = (&one_googol - value) / step;
+            remaining_ticks
+                .to_string()
+                .parse::<f64>()
+                .ok()
+                .map(|remaining| simulated_seconds + remaining * tick_secs)
+        } else {
+            None
+        }
+    };
+
+    let report = Report {
+        reached,
+        ticks,
+        simulated_seconds,
+        projected_seconds,
+        digits,
+        upgrades,
+    };
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+        Format::Csv => print_csv(&report),
+    }
+}
+
+/// Creates the synthetic player population
+fn players(args: &Args, tick_secs: f64, rng: &mut StdRng) -> Vec<Player> {
+    let sides = [
+        (CounterState::Increment, args.clients),
+        (CounterState::Decrement, args.saboteurs),
+        (CounterState::Pending, args.idle),
+    ];
+    sides
+        .into_iter()
+        .flat_map(|(counter_state, count)| (0..count).map(move |_| counter_state.clone()))
+        .enumerate()
+        .map(|(i, counter_state)| {
+            // Every player clicks at 50% to 150% of the average rate
+            let rate = match counter_state {
+                CounterState::Pending => 0.0,
+                _ => args.click_rate * rng.gen_range(0.5..=1.5),
+            };
+            Player {
+                counter_state,
+                clicks_per_tick: rate * tick_secs,
+                split_vote: i,
+            }
+        })
+        .collect()
+}
+
+/// Picks the vote of a player for a new poll
+fn pick_vote(
+    voting: Voting,
+    abstain: f64,
+    player: &Player,
+    options: &[PollOption],
+    rng: &mut StdRng,
+) -> PollState {
+    if options.is_empty() {
+        return PollState::Pending;
+    }
+    if rng.gen_bool(abstain.clamp(0.0, 1.0)) {
+        return PollState::Abstain;
+    }
+    let option = match voting {
+        Voting::Base => &options[0],
+        Voting::Exponent => &options[options.len() - 1],
+        Voting::Random => &options[rng.gen_range(0..options.len())],
+        Voting::Split => &options[player.split_vote % options.len()],
+    };
+    PollState::Vote(option.id.clone())
+}
+
+/// Samples the clicks of one tick, keeping the expected value
+fn clicks(expected: f64, rng: &mut StdRng) -> usize {
+    let whole = expected.floor();
+    whole as usize + rng.gen_bool((expected - whole).clamp(0.0, 1.0)) as usize
+}
+
+/// Prints the report as csv, the digit curve first and the upgrade history second
+fn print_csv(report: &Report) {
+    println!("tick,seconds,digits");
+    for point in &report.digits {
+        println!("{},{},{}", point.tick, point.seconds, point.digits);
+    }
+    println!();
+    println!("tick,seconds,level,choice,base,exponent");
+    for point in &report.upgrades {
+        println!(
+            "{},{},{},{},{},{}",
+            point.tick,
+            point.seconds,
+            point.level,
+            point.choice.as_deref().unwrap_or_default(),
+            point.base,
+            point.exponent
+        );
+    }
+    eprintln!(
+        "reached: {}, ticks: {}, simulated: {}s, projected: {}",
+        report.reached,
+        report.ticks,
+        report.simulated_seconds,
+        report
+            .projected_seconds
+            .map_or("unknown".to_string(), |s| format!("{s:.3e}s"))
+    );
+}
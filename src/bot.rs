@@ -0,0 +1,199 @@
+use serde::Serialize;
+use std::{collections::HashMap, time::Instant};
+
+/// Gap in milliseconds below which consecutive actions belong to the same burst
+const BURST_GAP_MS: f64 = 80.0;
+/// Longest burst a human keeps up, about 12 clicks per second for 5 seconds
+const MAX_HUMAN_BURST: usize = 60;
+/// Samples required before the click regularity is judged
+const MIN_INTERVALS: u64 = 30;
+/// Counter and poll commands required before their predictability is judged
+const MIN_SWITCHES: usize = 20;
+/// Vote ids with their own command, in the order a client first votes for them
+const VOTE_SLOTS: usize = 5;
+/// Distinct commands whose transitions are tracked: `increment`, `decrement`, `abstain`
+/// and the votes
+const COMMANDS: usize = 3 + VOTE_SLOTS;
+
+/// How the contributions of flagged clients are treated
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum BotPolicy {
+    /// Only flag them for the admins
+    #[default]
+    Monitor,
+    /// Scale their action clicks by one minus their score
+    Discount,
+    /// Ignore their clicks and votes entirely
+    Ignore,
+}
+
+/// Bot detection settings
+#[derive(Debug, Clone, Copy)]
+pub struct BotConfig {
+    pub policy: BotPolicy,
+    /// Score from which a client counts as automated
+    pub threshold: f64,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            policy: BotPolicy::Monitor,
+            threshold: 0.8,
+        }
+    }
+}
+
+impl BotConfig {
+    /// Weight of a client's input, `1` for humans and `0` for ignored bots
+    pub fn weight(&self, stats: &BotStats) -> f64 {
+        let score = stats.score();
+        if score < self.threshold {
+            return 1.0;
+        }
+        match self.policy {
+            BotPolicy::Monitor => 1.0,
+            BotPolicy::Discount => 1.0 - score,
+            BotPolicy::Ignore => 0.0,
+        }
+    }
+}
+
+/// Per-client timing statistics of the received commands
+#[derive(Debug, Default, Clone)]
+pub struct BotStats {
+    last_action: Option<Instant>,
+    /// Action intervals in milliseconds, as running mean and sum of squared deviations
+    intervals: u64,
+    interval_mean: f64,
+    interval_m2: f64,
+    burst: usize,
+    longest_burst: usize,
+    /// Transitions between counter and poll commands
+    last_switch: Option<(usize, Instant)>,
+    switch_mean: f64,
+    transitions: HashMap<(usize, usize), usize>,
+    /// Vote ids by slot
+    votes: Vec<String>,
+}
+
+impl BotStats {
+    /// Records an `action` click
+    pub fn action(&mut self, now: Instant) {
+        if let Some(last) = self.last_action {
+            let interval = now.duration_since(last).as_secs_f64() * 1000.0;
+            // Welford's online variance
+            self.intervals += 1;
+            let delta = interval - self.interval_mean;
+            self.interval_mean += delta / self.intervals as f64;
+            self.interval_m2 += delta * (interval - self.interval_mean);
+
+            if interval < BURST_GAP_MS {
+                self.burst += 1;
+            } else {
+                self.burst = 1;
+            }
+        } else {
+            self.burst = 1;
+        }
+        self.longest_burst = self.longest_burst.max(self.burst);
+        self.last_action = Some(now);
+    }
+
+    /// Records a counter or poll command, e.g. `increment` or `vote base`
+    pub fn command(&mut self, command: &str, now: Instant) {
+        let index = match command {
+            "increment" => 0,
+            "decrement" => 1,
+            "abstain" => 2,
+            command => match command.strip_prefix("vote ") {
+                Some(id) => 3 + self.vote_slot(id),
+                None => return,
+            },
+        };
+        if let Some((last, at)) = self.last_switch {
+            *self.transitions.entry((last, index)).or_default() += 1;
+            let total: usize = self.transitions.values().sum();
+            let interval = now.duration_since(at).as_secs_f64() * 1000.0;
+            self.switch_mean += (interval - self.switch_mean) / total as f64;
+        }
+        self.last_switch = Some((index, now));
+    }
+
+    /// Slot of a vote id, ids beyond the slots share the last one
+    fn vote_slot(&mut self, id: &str) -> usize {
+        if let Some(slot) = self.votes.iter().position(|vote| vote == id) {
+            return slot;
+        }
+        if self.votes.len() < VOTE_SLOTS {
+            self.votes.push(id.to_string());
+            return self.votes.len() - 1;
+        }
+        VOTE_SLOTS - 1
+    }
+
+    /// Coefficient of variation of the action intervals, humans are far from constant
+    pub fn interval_variation(&self) -> Option<f64> {
+        if self.intervals < MIN_INTERVALS || self.interval_mean <= 0.0 {
+            return None;
+        }
+        let variance = self.interval_m2 / (self.intervals - 1) as f64;
+        Some(variance.sqrt() / self.interval_mean)
+    }
+
+    /// Conditional entropy in bits of the next command given the previous one,
+    /// scripted toggling is perfectly predictable
+    pub fn command_entropy(&self) -> Option<f64> {
+        let total: usize = self.transitions.values().sum();
+        if total < MIN_SWITCHES {
+            return None;
+        }
+        let mut entropy = 0.0;
+        for from in 0..COMMANDS {
+            let row: Vec<f64> = (0..COMMANDS)
+                .map(|to| *self.transitions.get(&(from, to)).unwrap_or(&0) as f64)
+                .collect();
+            let row_total: f64 = row.iter().sum();
+            for count in row.into_iter().filter(|count| *count > 0.0) {
+                let p = count / row_total;
+                entropy -= row_total / total as f64 * p * p.log2();
+            }
+        }
+        Some(entropy)
+    }
+
+    /// Likelihood between 0 and 1 that the client is automated
+    pub fn score(&self) -> f64 {
+        let regularity = self
+            .interval_variation()
+            .map_or(0.0, |cv| ((0.3 - cv) / 0.25).clamp(0.0, 1.0));
+        let burst = ((self.longest_burst as f64 - MAX_HUMAN_BURST as f64) / MAX_HUMAN_BURST as f64)
+            .clamp(0.0, 1.0);
+        // Humans switch sides predictably too, but not several times a second
+        let predictability = self.command_entropy().map_or(0.0, |entropy| {
+            ((0.5 - entropy) / 0.5).clamp(0.0, 1.0)
+                * ((1000.0 - self.switch_mean) / 750.0).clamp(0.0, 1.0)
+        });
+        regularity.max(burst).max(predictability)
+    }
+
+    pub fn summary(&self) -> BotSummary {
+        BotSummary {
+            score: self.score(),
+            interval_mean_ms: self.interval_mean,
+            interval_variation: self.interval_variation(),
+            longest_burst: self.longest_burst,
+            command_entropy: self.command_entropy(),
+        }
+    }
+}
+
+/// Serializable view of the statistics
+#[derive(Debug, Clone, Serialize)]
+pub struct BotSummary {
+    pub score: f64,
+    pub interval_mean_ms: f64,
+    pub interval_variation: Option<f64>,
+    pub longest_burst: usize,
+    pub command_entropy: Option<f64>,
+}
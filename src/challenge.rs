@@ -0,0 +1,183 @@
+use aws_lc_rs::{
+    digest::{digest, SHA256},
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Serialize;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+
+use crate::{util, ws::WebSocketState};
+
+/// Proof-of-work settings for websocket joins
+#[derive(Debug, Clone, Copy)]
+pub struct ChallengeConfig {
+    /// Leading zero bits required of the solution hash
+    pub difficulty: u32,
+    /// Connected clients from which every doubling adds one bit of difficulty
+    pub load_threshold: usize,
+    /// Upper bound of the adapted difficulty
+    pub max_difficulty: u32,
+    /// Seconds a challenge stays valid
+    pub ttl: u64,
+}
+
+impl ChallengeConfig {
+    /// Difficulty for the given number of connected clients
+    pub fn difficulty(&self, clients: usize) -> u32 {
+        let mut difficulty = self.difficulty;
+        let mut threshold = self.load_threshold.max(1);
+        while clients >= threshold && difficulty < self.max_difficulty {
+            difficulty += 1;
+            threshold = threshold.saturating_mul(2);
+        }
+        difficulty
+    }
+}
+
+/// Issues and verifies signed proof-of-work challenges
+pub struct Challenger {
+    pub config: ChallengeConfig,
+    key: hmac::Key,
+    /// Solved challenges until they expire, each may only be used once
+    used: Mutex<HashMap<String, u64>>,
+}
+
+impl std::fmt::Debug for Challenger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Challenger")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A challenge as sent to the client
+#[derive(Debug, Serialize)]
+pub struct Challenge {
+    /// Signed token in the format `nonce.expires.difficulty.signature`
+    pub challenge: String,
+    pub difficulty: u32,
+}
+
+/// Why a challenge was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeError {
+    Missing,
+    Malformed,
+    Expired,
+    Reused,
+    Unsolved,
+}
+
+impl std::fmt::Display for ChallengeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "no challenge provided"),
+            Self::Malformed => write!(f, "challenge is malformed or forged"),
+            Self::Expired => write!(f, "challenge expired"),
+            Self::Reused => write!(f, "challenge was already used"),
+            Self::Unsolved => write!(f, "solution does not meet the difficulty"),
+        }
+    }
+}
+
+impl Challenger {
+    /// Creates a challenger with a random signing key, challenges do not survive restarts
+    pub fn new(config: ChallengeConfig) -> Self {
+        let key = hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new()).unwrap();
+        Self {
+            config,
+            key,
+            used: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Issues a challenge at the unix time `now`
+    pub fn issue(&self, clients: usize, now: u64) -> Challenge {
+        let mut nonce = [0; 16];
+        SystemRandom::new().fill(&mut nonce).unwrap();
+        let difficulty = self.config.difficulty(clients);
+        let payload = format!(
+            "{}.{}.{difficulty}",
+            URL_SAFE_NO_PAD.encode(nonce),
+            now + self.config.ttl
+        );
+        let signature = hmac::sign(&self.key, payload.as_bytes());
+        Challenge {
+            challenge: format!("{payload}.{}", URL_SAFE_NO_PAD.encode(signature)),
+            difficulty,
+        }
+    }
+
+    /// Verifies a solved challenge at the unix time `now` and marks it as used
+    pub async fn verify(
+        &self,
+        challenge: Option<&str>,
+        solution: Option<&str>,
+        now: u64,
+    ) -> Result<(), ChallengeError> {
+        let (Some(challenge), Some(solution)) = (challenge, solution) else {
+            return Err(ChallengeError::Missing);
+        };
+        let (payload, signature) = challenge
+            .rsplit_once('.')
+            .ok_or(ChallengeError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| ChallengeError::Malformed)?;
+        hmac::verify(&self.key, payload.as_bytes(), &signature)
+            .map_err(|_| ChallengeError::Malformed)?;
+
+        let mut parts = payload.split('.').skip(1);
+        let (Some(Ok(expires)), Some(Ok(difficulty))) = (
+            parts.next().map(str::parse::<u64>),
+            parts.next().map(str::parse::<u32>),
+        ) else {
+            return Err(ChallengeError::Malformed);
+        };
+        if expires < now {
+            return Err(ChallengeError::Expired);
+        }
+        if solution.len() > 64 || leading_zeros(challenge, solution) < difficulty {
+            return Err(ChallengeError::Unsolved);
+        }
+
+        let mut used = self.used.lock().await;
+        used.retain(|_, expires| *expires >= now);
+        if used.insert(challenge.to_string(), expires).is_some() {
+            return Err(ChallengeError::Reused);
+        }
+        Ok(())
+    }
+}
+
+/// Leading zero bits of `sha256("{challenge}:{solution}")`
+pub fn leading_zeros(challenge: &str, solution: &str) -> u32 {
+    let hash = digest(&SHA256, format!("{challenge}:{solution}").as_bytes());
+    let mut zeros = 0;
+    for byte in hash.as_ref() {
+        zeros += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    zeros
+}
+
+/// Handler for `GET /api/challenge`, not found if joins are not gated
+pub async fn challenge(
+    State((challenger, state)): State<(Option<Arc<Challenger>>, Arc<WebSocketState>)>,
+) -> Response {
+    let Some(challenger) = challenger else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let clients = state.clients.read().await.len();
+    Json(challenger.issue(clients, util::now_secs())).into_response()
+}
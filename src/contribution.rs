@@ -0,0 +1,183 @@
+use num_bigint::BigInt;
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use tracing::info;
+
+use crate::{account::Account, counter::Step, util, ws::CounterState};
+
+/// Anonymous identities kept at most, as clients choose them freely
+pub const MAX_ANONYMOUS_CONTRIBUTORS: usize = 10_000;
+
+/// Share of a single client in one counter update
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Share {
+    pub added: BigInt,
+    pub sabotaged: BigInt,
+}
+
+/// Splits a step between the clients in `counter_all`, the result has the same order.
+///
+/// The step is split evenly among the clients of a side, the action modifier
+/// proportionally to their action clicks. Shares are rounded down.
+pub fn attribute(step: &Step, counter_all: &[(CounterState, usize)]) -> Vec<Share> {
+    let side = |state: CounterState| {
+        let members = counter_all.iter().filter(|(s, _)| *s == state).count();
+        let clicks: usize = counter_all
+            .iter()
+            .filter(|(s, _)| *s == state)
+            .map(|(_, clicks)| clicks)
+            .sum();
+        (members, clicks)
+    };
+    let (incrementers, increment_clicks) = side(CounterState::Increment);
+    let (decrementers, decrement_clicks) = side(CounterState::Decrement);
+
+    let share_of = |step: &BigInt, modifier: &BigInt, members, clicks, own_clicks| {
+        let mut share = step / BigInt::from(members);
+        if clicks > 0 {
+            share += modifier * BigInt::from(own_clicks) / BigInt::from(clicks);
+        }
+        share
+    };
+
+    counter_all
+        .iter()
+        .map(|(state, clicks)| match state {
+            CounterState::Increment => Share {
+                added: share_of(
+                    &step.increment,
+                    &step.increment_modifier,
+                    incrementers,
+                    increment_clicks,
+                    *clicks,
+                ),
+                sabotaged: BigInt::zero(),
+            },
+            CounterState::Decrement => Share {
+                added: BigInt::zero(),
+                sabotaged: share_of(
+                    &step.decrement,
+                    &step.decrement_modifier,
+                    decrementers,
+                    decrement_clicks,
+                    *clicks,
+                ),
+            },
+            CounterState::Pending => Share::default(),
+        })
+        .collect()
+}
+
+/// Accumulated contribution of a stable identity
+#[derive(Debug, Default, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct Contributor {
+    pub nickname: Option<String>,
+    #[serde(
+        serialize_with = "util::serialize_bigint",
+        deserialize_with = "util::deserialize_bigint"
+    )]
+    pub added: BigInt,
+    #[serde(
+        serialize_with = "util::serialize_bigint",
+        deserialize_with = "util::deserialize_bigint"
+    )]
+    pub sabotaged: BigInt,
+}
+
+/// Anonymous leaderboard entry, the identity itself is never exposed
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub nickname: Option<String>,
+    #[serde(serialize_with = "util::serialize_bigint")]
+    pub added: BigInt,
+    #[serde(serialize_with = "util::serialize_bigint")]
+    pub sabotaged: BigInt,
+}
+
+/// Persistent contribution totals per identity
+#[derive(Debug, Default, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct Contributions {
+    pub contributors: HashMap<String, Contributor>,
+}
+
+impl Contributions {
+    /// Loads the contributions from a json file if it exists
+    pub fn load_from_file(&mut self, path: &PathBuf) {
+        if path.exists() {
+            let file = File::open(path).unwrap();
+            let reader = BufReader::new(file);
+            *self = serde_json::from_reader(reader).unwrap();
+            info!("Loaded Contributions from file: {:?}", path);
+        }
+    }
+
+    /// Saves the contributions to a json file
+    pub fn save_to_file(&self, path: &PathBuf) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Adds a share to the totals of `identity`, empty shares are skipped. Once
+    /// [`MAX_ANONYMOUS_CONTRIBUTORS`] are stored, a new anonymous identity replaces the one
+    /// which added the least.
+    pub fn add(&mut self, identity: &str, nickname: Option<&String>, share: &Share) {
+        if share.added.is_zero() && share.sabotaged.is_zero() {
+            return;
+        }
+        if !self.contributors.contains_key(identity) && !Account::is_identity(identity) {
+            self.make_room();
+        }
+        let contributor = self.contributors.entry(identity.to_string()).or_default();
+        if nickname.is_some() {
+            contributor.nickname = nickname.cloned();
+        }
+        contributor.added += &share.added;
+        contributor.sabotaged += &share.sabotaged;
+    }
+
+    /// Drops the anonymous identity which added the least if there are too many
+    fn make_room(&mut self) {
+        if self.contributors.len() < MAX_ANONYMOUS_CONTRIBUTORS {
+            return;
+        }
+        let anonymous = self
+            .contributors
+            .iter()
+            .filter(|(identity, _)| !Account::is_identity(identity));
+        if anonymous.clone().count() < MAX_ANONYMOUS_CONTRIBUTORS {
+            return;
+        }
+        let smallest = anonymous
+            .min_by(|(_, a), (_, b)| a.added.cmp(&b.added).then(b.sabotaged.cmp(&a.sabotaged)))
+            .map(|(identity, _)| identity.clone());
+        if let Some(identity) = smallest {
+            self.contributors.remove(&identity);
+        }
+    }
+
+    /// Returns the `limit` identities which added the most
+    pub fn leaderboard(&self, limit: usize) -> Vec<LeaderboardEntry> {
+        let mut contributors: Vec<_> = self.contributors.values().collect();
+        contributors.sort_by(|a, b| b.added.cmp(&a.added).then(a.sabotaged.cmp(&b.sabotaged)));
+        contributors
+            .into_iter()
+            .take(limit)
+            .enumerate()
+            .map(|(i, c)| LeaderboardEntry {
+                rank: i + 1,
+                nickname: c.nickname.clone(),
+                added: c.added.clone(),
+                sabotaged: c.sabotaged.clone(),
+            })
+            .collect()
+    }
+}
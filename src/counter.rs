@@ -2,13 +2,21 @@ use num_bigint::BigInt;
 use num_traits::{Signed, Zero};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     fs::File,
     io::{BufReader, BufWriter},
     path::PathBuf,
+    time::Duration,
 };
 use tracing::info;
 
 use crate::{
+    milestone::Progress,
+    poll::{PollResult, PollRules, TieBreak},
+    schedule::Modifier,
+    season::{Completion, Season},
+    team::Teams,
+    upgrade::Catalogue,
     util,
     ws::{CounterState, PollState},
 };
@@ -19,21 +27,39 @@ pub struct Counter {
     pub count: Count,
     pub poll: Option<Poll>,
     pub upgrade: Upgrade,
-}
-
-impl Default for Counter {
-    fn default() -> Self {
-        Self::new()
-    }
+    #[serde(default)]
+    pub season: Season,
+    /// Finished polls of the season, oldest first
+    #[serde(default)]
+    pub poll_history: Vec<PollResult>,
+    /// Milestones of the season
+    #[serde(default)]
+    pub milestones: Progress,
+    /// Both sides with their own upgrades, in team mode
+    #[serde(default)]
+    pub teams: Option<Teams>,
 }
 
 impl Counter {
-    /// Creates a new Counter with count initialized to zero
-    pub fn new() -> Self {
+    /// Creates a new Counter with count initialized to zero, its season starting at `now`
+    pub fn new(now: u64) -> Self {
         Self {
             count: Count::default(),
             poll: None,
             upgrade: Upgrade::default(),
+            season: Season::new(1, now),
+            poll_history: Vec::new(),
+            milestones: Progress::default(),
+            teams: None,
+        }
+    }
+
+    /// Creates a fresh counter for the season following this one, starting at `now`
+    pub fn next_season(&self, now: u64) -> Self {
+        Self {
+            season: Season::new(self.season.number + 1, now),
+            teams: self.teams.as_ref().map(|_| Teams::default()),
+            ..Self::new(now)
         }
     }
 
@@ -64,61 +90,166 @@ impl Counter {
         self.count.value.to_str_radix(10)
     }
 
-    /// Updates the counter based on the provided counter states
-    pub fn update_count(&mut self, counter_all: &[(CounterState, usize)]) {
+    /// Compares the state of two counters, ignoring the tick count of their seasons
+    pub fn same_state(&self, other: &Self) -> bool {
+        self.count == other.count
+            && self.poll == other.poll
+            && self.upgrade == other.upgrade
+            && self.poll_history == other.poll_history
+            && self.milestones == other.milestones
+            && self.teams == other.teams
+            && Season {
+                ticks: other.season.ticks,
+                ..self.season.clone()
+            } == other.season
+    }
+
+    /// Returns the result of the last finished poll
+    pub fn last_poll(&self) -> Option<&PollResult> {
+        self.poll_history.last()
+    }
+
+    /// Returns the running poll, the one of the given team in team mode
+    pub fn running_poll(&self, team: Option<&CounterState>) -> Option<&Poll> {
+        match (&self.teams, team) {
+            (Some(teams), Some(CounterState::Increment)) => teams.increment.poll.as_ref(),
+            (Some(teams), Some(CounterState::Decrement)) => teams.decrement.poll.as_ref(),
+            _ => self.poll.as_ref(),
+        }
+    }
+
+    /// Returns true if the counter reached One Googol
+    pub fn is_googol(&self) -> bool {
+        self.count.value == one_googol()
+    }
+
+    /// Freezes the counter once One Googol is reached and returns the completion record
+    pub fn check_completion(&mut self, now: u64) -> Option<Completion> {
+        if self.season.completed_at.is_some() || !self.is_googol() {
+            return None;
+        }
+        self.season.completed_at = Some(now);
+        self.poll = None;
+        if let Some(teams) = &mut self.teams {
+            teams.increment.poll = None;
+            teams.decrement.poll = None;
+        }
+        Some(Completion::new(self, now))
+    }
+
+    /// Updates the counter based on the provided counter states and the modifier of the
+    /// running events, returns the applied step
+    pub fn update_count(
+        &mut self,
+        counter_all: &[(CounterState, usize)],
+        modifier: &Modifier,
+    ) -> Step {
+        if self.season.completed_at.is_some() {
+            return Step::default();
+        }
+        self.season.peak_players = self.season.peak_players.max(counter_all.len());
+        self.season.ticks += 1;
+
         let mut counter_states = vec![];
-        self.count.accumulated_actions = 0;
+        let mut increment_clicks = 0;
+        let mut decrement_clicks = 0;
 
         for (counter_state, action_clicks) in counter_all {
             counter_states.push(counter_state.clone());
             let action_clicks = (*action_clicks) as i64;
             if counter_state == &CounterState::Increment {
-                self.count.accumulated_actions += action_clicks;
+                increment_clicks += action_clicks;
             } else if counter_state == &CounterState::Decrement {
-                self.count.accumulated_actions -= action_clicks;
+                decrement_clicks += action_clicks;
             }
         }
+        self.count.accumulated_actions = increment_clicks - decrement_clicks;
 
         self.count.meter = CounterState::meter_counter(&counter_states);
 
         let cmp_step = Self::compute_step(&self.count.value);
 
-        // Compute the modifier in BigInt to avoid overflow
-        let actions_modifier =
-            BigInt::from(self.count.accumulated_actions) * self.upgrade.modifier(cmp_step);
-        let positive_modifier = if actions_modifier > BigInt::zero() {
-            actions_modifier.clone()
-        } else {
-            BigInt::zero()
+        // Without teams both sides share the upgrades, boosts only help the incrementers
+        let (inc, dec) = match &self.teams {
+            Some(teams) => (&teams.increment.upgrade, &teams.decrement.upgrade),
+            None => (&self.upgrade, &self.upgrade),
         };
-        let negative_modifier = if actions_modifier < BigInt::zero() {
-            -actions_modifier.clone()
-        } else {
-            BigInt::zero()
+        let (positive_modifier, negative_modifier) = match &self.teams {
+            Some(_) => (
+                inc.action_modifier(increment_clicks, cmp_step, modifier),
+                dec.action_modifier(decrement_clicks, cmp_step, modifier),
+            ),
+            None => {
+                let actions_modifier =
+                    inc.action_modifier(self.count.accumulated_actions, cmp_step, modifier);
+                if actions_modifier > BigInt::zero() {
+                    (actions_modifier, BigInt::zero())
+                } else {
+                    (BigInt::zero(), -actions_modifier)
+                }
+            }
+        };
+
+        let increment_factor = inc.increment_factor();
+        // The decrementers' resistance shields them from the increments
+        let (decrement_factor, increment_percent) = match &self.teams {
+            Some(_) => (
+                dec.increment_factor(),
+                (100 - dec.decrement_resistance.min(100)) as u64,
+            ),
+            None => (BigInt::from(1), 100),
+        };
+        // Percent of the decrement left by the resistance and the events
+        let decrement_percent =
+            (100 - inc.decrement_resistance.min(100)) as u64 * modifier.decrement as u64 / 100;
+        let step = Step {
+            increment: (BigInt::from(self.count.meter.increment) * BigInt::from(inc.base))
+                .pow(cmp_step + inc.exponent)
+                * &increment_factor
+                * modifier.step
+                * increment_percent
+                / 10_000,
+            increment_modifier: positive_modifier * &increment_factor * increment_percent / 100,
+            decrement: (BigInt::from(self.count.meter.decrement) * BigInt::from(dec.base))
+                .pow(cmp_step + dec.exponent)
+                * &decrement_factor
+                * modifier.step
+                * decrement_percent
+                / 10_000,
+            decrement_modifier: negative_modifier * &decrement_factor * decrement_percent / 100,
         };
 
-        let step_increment = (BigInt::from(self.count.meter.increment)
-            * BigInt::from(self.upgrade.base))
-        .pow(cmp_step + self.upgrade.exponent)
-            + positive_modifier;
+        if let Some(teams) = &mut self.teams {
+            teams.record(
+                self.count.meter.increment,
+                self.count.meter.decrement,
+                &step,
+            );
+        }
 
-        self.count.value += step_increment;
-        let one_googol = BigInt::parse_bytes(util::ONE_GOOGOL.as_bytes(), 10).unwrap();
+        self.count.value += &step.increment + &step.increment_modifier;
+        let one_googol = one_googol();
         if self.count.value > one_googol {
             self.count.value = one_googol.clone();
         }
 
-        let step_decrement = (BigInt::from(self.count.meter.decrement)
-            * BigInt::from(self.upgrade.base))
-        .pow(cmp_step + self.upgrade.exponent)
-            + negative_modifier;
-
         if self.count.value != one_googol {
-            self.count.value -= step_decrement;
+            self.count.value -= &step.decrement + &step.decrement_modifier;
             if self.count.value < BigInt::zero() {
                 self.count.value = BigInt::zero();
             }
         }
+
+        step
+    }
+
+    /// Counts down the running frenzies by a tick of the given duration
+    pub fn tick_frenzies(&mut self, tick: Duration) {
+        self.upgrade.tick_frenzy(tick);
+        if let Some(teams) = &mut self.teams {
+            teams.increment.upgrade.tick_frenzy(tick);
+            teams.decrement.upgrade.tick_frenzy(tick);
+        }
     }
 
     /// Function to compute the square root of the number of digits in the counter
@@ -128,29 +259,69 @@ impl Counter {
         (digit_length as f64).sqrt() as u32
     }
 
-    /// Updates the poll based on the provided poll states
-    pub fn update_poll(&mut self, poll_states: &[PollState]) {
-        if self.is_at_upgrade() {
-            if let Some(poll) = &mut self.poll {
-                poll.amplification += 1;
-            } else {
-                self.poll = Some(Poll::new());
-            }
+    /// Updates the polls based on the provided weighted poll states of the clients on each side,
+    /// `tick` is the duration of one update. With teams each side votes its own upgrades.
+    pub fn update_poll(
+        &mut self,
+        poll_states: &[(CounterState, PollState, u32)],
+        tick: Duration,
+        rules: &PollRules,
+        catalogue: &Catalogue,
+    ) {
+        if self.season.completed_at.is_some() {
+            return;
         }
 
-        if let Some(poll) = &mut self.poll {
-            poll.meter = PollState::meter_poll(poll_states);
+        // Seeded by season and poll, so a restart offers the same upgrades
+        let seed = (self.season.number as u64) << 32 | self.season.polls as u64;
+        let at_upgrade = self.is_at_upgrade();
+        let votes = |side: Option<&CounterState>| -> Vec<_> {
+            poll_states
+                .iter()
+                .filter(|(state, _, _)| side.is_none_or(|side| state == side))
+                .map(|(_, poll_state, weight)| (poll_state.clone(), *weight))
+                .collect()
+        };
 
-            if poll.tick() {
-                if poll.meter.base > poll.meter.exponent {
-                    self.upgrade.base += poll.amplification;
-                    self.upgrade.last_upgrade = PollState::Base;
-                } else {
-                    self.upgrade.exponent += poll.amplification;
-                    self.upgrade.last_upgrade = PollState::Exponent;
+        match &mut self.teams {
+            None => {
+                if at_upgrade && self.poll.is_none() {
+                    self.season.polls += 1;
+                }
+                let track = Track {
+                    poll: &mut self.poll,
+                    upgrade: &mut self.upgrade,
+                    history: &mut self.poll_history,
+                    team: None,
+                };
+                let winner = track.update(at_upgrade, seed, &votes(None), tick, rules, catalogue);
+                if let Some(winner) = winner {
+                    self.season.upgrade_path.push(winner);
+                }
+            }
+            Some(teams) => {
+                if at_upgrade && (teams.increment.poll.is_none() || teams.decrement.poll.is_none())
+                {
+                    self.season.polls += 1;
+                }
+                for (side, team) in [
+                    (CounterState::Increment, &mut teams.increment),
+                    (CounterState::Decrement, &mut teams.decrement),
+                ] {
+                    team.upgrade.level = self.upgrade.level;
+                    let side_votes = votes(Some(&side));
+                    let track = Track {
+                        poll: &mut team.poll,
+                        upgrade: &mut team.upgrade,
+                        history: &mut self.poll_history,
+                        team: Some(side),
+                    };
+                    if let Some(winner) =
+                        track.update(at_upgrade, seed, &side_votes, tick, rules, catalogue)
+                    {
+                        team.upgrade_path.push(winner);
+                    }
                 }
-                // reset poll
-                self.poll = None;
             }
         }
     }
@@ -168,6 +339,97 @@ impl Counter {
     }
 }
 
+/// Amounts added and removed by a single counter update, before clamping.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// Step of the incrementing clients
+    pub increment: BigInt,
+    /// Action modifier added by the incrementing clients
+    pub increment_modifier: BigInt,
+    /// Step of the decrementing clients
+    pub decrement: BigInt,
+    /// Action modifier removed by the decrementing clients
+    pub decrement_modifier: BigInt,
+}
+
+/// Upgrades decided by their own polls, of the whole game or of a team
+struct Track<'a> {
+    poll: &'a mut Option<Poll>,
+    upgrade: &'a mut Upgrade,
+    history: &'a mut Vec<PollResult>,
+    /// Side voting the polls, in team mode
+    team: Option<CounterState>,
+}
+
+impl Track<'_> {
+    /// Starts or amplifies a poll at an upgrade, ticks it and applies the winner once
+    /// finished. Returns the id of the applied upgrade.
+    fn update(
+        self,
+        at_upgrade: bool,
+        seed: u64,
+        poll_states: &[(PollState, u32)],
+        tick: Duration,
+        rules: &PollRules,
+        catalogue: &Catalogue,
+    ) -> Option<String> {
+        if at_upgrade {
+            if let Some(poll) = self.poll {
+                poll.amplification += 1;
+            } else {
+                *self.poll = Some(Poll::new(tick, catalogue.draw(seed, rules.options)));
+            }
+        }
+
+        let poll = self.poll.as_mut()?;
+        // Polls from before the catalogue
+        if poll.options.is_empty() {
+            poll.options = catalogue.draw(seed, rules.options);
+        }
+        // Polls from before their length was stored
+        if poll.tick_ms == 0 {
+            poll.length = Poll::new(tick, vec![]).length.max(poll.time_remaining);
+            poll.tick_ms = tick.as_millis() as u64;
+        }
+        poll.meter = PollState::meter_poll(poll_states, &poll.options);
+        if !poll.tick(rules, tick) {
+            return None;
+        }
+
+        let winner = rules.winner(
+            &poll.meter,
+            &poll.options,
+            self.upgrade.last_upgrade.as_deref(),
+        );
+        if let Some((winner, tie_broken)) = &winner {
+            if let Some(kind) = catalogue.get(winner) {
+                kind.effect.apply(self.upgrade, poll.amplification);
+            }
+            self.upgrade.last_upgrade = Some(winner.clone());
+            self.history.push(PollResult {
+                winner: winner.clone(),
+                tally: poll.meter.clone(),
+                amplification: poll.amplification,
+                ticks: poll.elapsed,
+                duration_ms: poll.elapsed as u64 * tick.as_millis() as u64,
+                tie_broken: *tie_broken,
+                level: self.upgrade.level,
+                base: self.upgrade.base,
+                exponent: self.upgrade.exponent,
+                team: self.team,
+            });
+        }
+        // reset poll
+        *self.poll = None;
+        winner.map(|(winner, _)| winner)
+    }
+}
+
+/// One Googol as BigInt
+fn one_googol() -> BigInt {
+    BigInt::parse_bytes(util::ONE_GOOGOL.as_bytes(), 10).unwrap()
+}
+
 #[derive(Debug, Default, Serialize, Clone, Deserialize, PartialEq, Eq)]
 pub struct Count {
     #[serde(
@@ -184,32 +446,74 @@ pub struct Poll {
     pub time_remaining: u32,
     pub amplification: u32,
     pub meter: PollMeter,
+    /// Ticks the poll has been running
+    #[serde(default)]
+    pub elapsed: u32,
+    /// Upgrades from the catalogue to vote for
+    #[serde(default)]
+    pub options: Vec<PollOption>,
+    /// Ticks the countdown started with
+    #[serde(default)]
+    pub length: u32,
+    /// Duration of a tick in milliseconds, to show the countdown in seconds
+    #[serde(default)]
+    pub tick_ms: u64,
 }
 
 impl Default for Poll {
     fn default() -> Self {
-        Self::new()
+        Self::new(
+            Duration::from_millis(util::UPDATE_PERIOD_MS),
+            Catalogue::default().draw(0, 2),
+        )
     }
 }
 
+/// An option of a poll
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct PollOption {
+    /// Id of the upgrade, sent with the `vote <id>` command
+    pub id: String,
+    pub label: String,
+}
+
 impl Poll {
-    pub fn new() -> Self {
+    /// Creates a poll between the `options` running `POLL_DURATION_SECS`, counted in ticks of the given duration
+    pub fn new(tick: Duration, options: Vec<PollOption>) -> Self {
+        let ticks =
+            Duration::from_secs(util::POLL_DURATION_SECS).as_millis() / tick.as_millis().max(1);
+        let ticks = ticks.clamp(1, u32::MAX as u128) as u32;
         Self {
-            time_remaining: 1200, // 300s * 4 -> 1/4s
+            time_remaining: ticks,
             amplification: 1,
             meter: PollMeter::default(),
+            elapsed: 0,
+            options,
+            length: ticks,
+            tick_ms: tick.as_millis() as u64,
         }
     }
 
-    /// Ticks a tick and returns true if `time_remaining == 0`
-    pub fn tick(&mut self) -> bool {
+    /// Ticks a tick of the given duration and returns true if the poll is finished.
+    ///
+    /// The countdown waits for the first vote and the quorum, with [`TieBreak::Extend`]
+    /// also while tied, and speeds up 4x once most players voted or abstained. At zero
+    /// the poll ends after the minimum runtime, even if tied.
+    pub fn tick(&mut self, rules: &PollRules, tick: Duration) -> bool {
+        self.elapsed = self.elapsed.saturating_add(1);
+        let voted = self.meter.voted();
+        let quorum = rules.quorum_met(&self.meter);
+        let paused_tie =
+            rules.tie_break == TieBreak::Extend && self.meter.leaders(&self.options).len() > 1;
+
         if self.time_remaining == 0 {
-            return true;
-        } else if self.meter.base == self.meter.exponent {
+            let min_ticks = rules.min_runtime.as_millis() / tick.as_millis().max(1);
+            return self.elapsed as u128 >= min_ticks && quorum;
+        } else if voted == 0 || !quorum || paused_tie {
             return false;
         }
 
-        if self.meter.pending < self.meter.base + self.meter.exponent {
+        if self.meter.pending < voted {
             self.time_remaining = self.time_remaining.saturating_sub(4);
         } else {
             self.time_remaining = self.time_remaining.saturating_sub(1);
@@ -221,9 +525,32 @@ impl Poll {
 #[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
 pub struct Upgrade {
     pub level: usize,
-    pub last_upgrade: PollState,
+    /// Id of the last applied upgrade
+    #[serde(default, deserialize_with = "util::deserialize_upgrade_id")]
+    pub last_upgrade: Option<String>,
     pub base: u32,
     pub exponent: u32,
+    /// Bonus on the action modifier in percent
+    #[serde(default)]
+    pub action_multiplier: u32,
+    /// Reduction of the decrements in percent. In team mode it shields the team it
+    /// belongs to, so the decrementers' resistance lowers the increments instead.
+    #[serde(default)]
+    pub decrement_resistance: u32,
+    /// Additional increments per tick
+    #[serde(default)]
+    pub tick_boost: u32,
+    #[serde(default)]
+    pub frenzy: Option<Frenzy>,
+}
+
+/// Temporary multiplier of the increment
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct Frenzy {
+    pub multiplier: u32,
+    /// Frenzies saved before this was stored end with the next tick
+    #[serde(default)]
+    pub remaining_ms: u64,
 }
 
 impl Default for Upgrade {
@@ -236,12 +563,42 @@ impl Upgrade {
     pub fn new() -> Self {
         Self {
             level: 0,
-            last_upgrade: PollState::default(),
+            last_upgrade: None,
             base: 1,
             exponent: 0,
+            action_multiplier: 0,
+            decrement_resistance: 0,
+            tick_boost: 0,
+            frenzy: None,
         }
     }
 
+    /// Action modifier of `clicks` with the action multiplier and the events applied
+    pub fn action_modifier(&self, clicks: i64, cmp_step: u32, modifier: &Modifier) -> BigInt {
+        // Compute the modifier in BigInt to avoid overflow
+        BigInt::from(clicks)
+            * self.modifier(cmp_step)
+            * (100 + self.action_multiplier as u64)
+            * modifier.action
+            / 10_000
+    }
+
+    /// Counts down a running frenzy by a tick of the given duration
+    pub fn tick_frenzy(&mut self, tick: Duration) {
+        if let Some(frenzy) = &mut self.frenzy {
+            frenzy.remaining_ms = frenzy.remaining_ms.saturating_sub(tick.as_millis() as u64);
+            if frenzy.remaining_ms == 0 {
+                self.frenzy = None;
+            }
+        }
+    }
+
+    /// Factor of the increment from tick boosts and a running frenzy
+    pub fn increment_factor(&self) -> BigInt {
+        let frenzy = self.frenzy.as_ref().map_or(1, |frenzy| frenzy.multiplier);
+        BigInt::from(1 + self.tick_boost as u64) * BigInt::from(frenzy)
+    }
+
     // Use BigInt for the modifier to avoid fixed-size integer overflow.
     pub fn modifier(&self, cmp_step: u32) -> BigInt {
         let factor = BigInt::from(self.level as i64 + 1) * BigInt::from(self.base as i64);
@@ -261,7 +618,28 @@ pub struct CountMeter {
 /// Client poll state count
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PollMeter {
-    pub base: u32,
-    pub exponent: u32,
+    /// Votes by option id
+    #[serde(default)]
+    pub votes: BTreeMap<String, u32>,
     pub pending: u32,
+    #[serde(default)]
+    pub abstain: u32,
+}
+
+impl PollMeter {
+    /// Votes cast for an option or abstaining
+    pub fn voted(&self) -> u32 {
+        self.votes.values().sum::<u32>() + self.abstain
+    }
+
+    /// Ids of the options with the most votes, in the order of `options`
+    pub fn leaders<'a>(&self, options: &'a [PollOption]) -> Vec<&'a str> {
+        let votes = |option: &PollOption| self.votes.get(&option.id).copied().unwrap_or(0);
+        let most = options.iter().map(votes).max().unwrap_or(0);
+        options
+            .iter()
+            .filter(|option| votes(option) == most)
+            .map(|option| option.id.as_str())
+            .collect()
+    }
 }
@@ -0,0 +1,185 @@
+use num_bigint::BigInt;
+use std::time::Duration;
+
+use crate::{
+    contribution::{self, Share},
+    counter::Counter,
+    event::Event,
+    milestone::Milestones,
+    poll::PollRules,
+    schedule::Modifier,
+    team::Teams,
+    upgrade::Catalogue,
+    util,
+    ws::{CounterState, PollState},
+};
+
+/// Rules of the game which are not part of the persisted counter
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    /// Duration of one tick
+    pub tick: Duration,
+    /// Delay after a completion before a new season starts, `None` keeps the game frozen
+    pub new_season_after: Option<Duration>,
+    pub poll: PollRules,
+    /// Upgrades the polls offer
+    pub upgrades: Catalogue,
+    pub milestones: Milestones,
+    /// Clients play in teams with their own upgrades and polls
+    pub teams: bool,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            tick: Duration::from_millis(util::UPDATE_PERIOD_MS),
+            new_season_after: None,
+            poll: PollRules::default(),
+            upgrades: Catalogue::default(),
+            milestones: Milestones::default(),
+            teams: false,
+        }
+    }
+}
+
+/// State of a single client during one tick
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ClientInput {
+    pub counter_state: CounterState,
+    pub action_clicks: usize,
+    pub poll_state: PollState,
+    /// Total added by the client, weighs its vote
+    pub added: BigInt,
+    /// Index of an earlier client of the same account, this one only adds its clicks there.
+    /// Links to later, unknown or duplicate clients are ignored.
+    pub duplicate_of: Option<usize>,
+}
+
+/// Everything a tick depends on
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TickInput {
+    /// Current unix time in seconds
+    pub now: u64,
+    pub clients: Vec<ClientInput>,
+    /// Combined modifier of the running events
+    pub modifier: Modifier,
+}
+
+/// Result of a tick
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TickOutput {
+    /// True if the counter state changed and should be broadcast
+    pub changed: bool,
+    /// Shares of the clients, in the order of `TickInput::clients`
+    pub shares: Vec<Share>,
+    pub events: Vec<Event>,
+}
+
+/// The game rules without any IO, clock or runtime.
+#[derive(Debug, Clone)]
+pub struct GameEngine {
+    pub counter: Counter,
+    pub config: EngineConfig,
+}
+
+impl GameEngine {
+    pub fn new(counter: Counter, config: EngineConfig) -> Self {
+        Self { counter, config }
+    }
+
+    /// Advances the game by one tick
+    pub fn tick(&mut self, input: &TickInput) -> TickOutput {
+        let before = self.counter.clone();
+        if self.config.teams != self.counter.teams.is_some() {
+            self.counter.teams = self.config.teams.then(Teams::default);
+        }
+
+        // Duplicates are folded into their first client, which plays and votes for both
+        let mut counter_states: Vec<(CounterState, usize)> = vec![];
+        let mut players = vec![];
+        for client in &input.clients {
+            let first = client
+                .duplicate_of
+                .and_then(|first| players.get(first).copied());
+            let player = match first.flatten() {
+                Some(player) => {
+                    let (_, clicks) = &mut counter_states[player];
+                    *clicks += client.action_clicks;
+                    None
+                }
+                None => {
+                    counter_states.push((client.counter_state.clone(), client.action_clicks));
+                    Some(counter_states.len() - 1)
+                }
+            };
+            players.push(player);
+        }
+        let poll_states: Vec<_> = input
+            .clients
+            .iter()
+            .zip(&players)
+            .filter(|(_, player)| player.is_some())
+            .map(|(client, _)| {
+                let weight = self.config.poll.weight(&client.added);
+                let counter_state = client.counter_state.clone();
+                (counter_state, client.poll_state.clone(), weight)
+            })
+            .collect();
+
+        self.counter.update_poll(
+            &poll_states,
+            self.config.tick,
+            &self.config.poll,
+            &self.config.upgrades,
+        );
+        let step = self.counter.update_count(&counter_states, &input.modifier);
+        self.counter.tick_frenzies(self.config.tick);
+        let player_shares = contribution::attribute(&step, &counter_states);
+        let shares = players
+            .iter()
+            .map(|player| player.map_or_else(Share::default, |p| player_shares[p].clone()))
+            .collect();
+
+        let mut events = vec![];
+        if let Some(teams) = &self.counter.teams {
+            let leader = before.teams.as_ref().map(|teams| &teams.leader);
+            if teams.leader != CounterState::Pending && leader != Some(&teams.leader) {
+                events.push(Event::Lead {
+                    team: teams.leader.clone(),
+                });
+            }
+        }
+        if self.counter.season.completed_at.is_none() {
+            let digits = self.counter.count_string().len();
+            let reached = self.config.milestones.check(
+                &mut self.counter.milestones,
+                digits,
+                counter_states.len(),
+                input.now,
+                self.counter.season.ticks,
+            );
+            events.extend(reached.into_iter().map(Event::Milestone));
+        }
+        if let Some(completion) = self.counter.check_completion(input.now) {
+            events.push(Event::Completed(completion));
+        }
+
+        if let (Some(completed_at), Some(delay)) = (
+            self.counter.season.completed_at,
+            self.config.new_season_after,
+        ) {
+            if input.now >= completed_at + delay.as_secs() {
+                self.counter = self.counter.next_season(input.now);
+                events.push(Event::NewSeason {
+                    season: self.counter.season.number,
+                });
+            }
+        }
+
+        TickOutput {
+            changed: !self.counter.same_state(&before),
+            shares,
+            events,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use serde::Serialize;
+
+use crate::{
+    milestone::Reached,
+    schedule::ActiveEvent,
+    season::Completion,
+    ws::{CounterState, PollState},
+};
+
+/// Game events sent to the clients next to the counter updates.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// One Googol was reached
+    Completed(Completion),
+    /// The counter was reset into a new season
+    NewSeason { season: u32 },
+    /// The currently connected players
+    Presence { players: Vec<Player> },
+    /// A milestone of the season was reached
+    Milestone(Reached),
+    /// The currently running timed events
+    Schedule { active: Vec<ActiveEvent> },
+    /// A team took the lead in team mode
+    Lead { team: CounterState },
+    /// The team of the client, only sent to it on join in team mode
+    Joined { team: CounterState },
+    /// A command of the client was rejected, only sent to it
+    Rejected { reason: String },
+}
+
+/// Publicly visible state of a connected player
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Player {
+    pub name: Option<String>,
+    pub counter_state: CounterState,
+    pub poll_state: PollState,
+}
+
+impl Event {
+    /// Serializes the event into a websocket message
+    pub fn to_message(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
@@ -0,0 +1,26 @@
+pub mod account;
+#[cfg(feature = "acme")]
+pub mod acme;
+pub mod admin;
+pub mod api;
+pub mod app;
+#[cfg(feature = "embed")]
+pub mod assets;
+pub mod bot;
+pub mod challenge;
+pub mod contribution;
+pub mod counter;
+pub mod engine;
+pub mod event;
+pub mod milestone;
+pub mod name;
+pub mod net;
+pub mod poll;
+pub mod schedule;
+pub mod season;
+pub mod security;
+pub mod team;
+pub mod tls;
+pub mod upgrade;
+pub mod util;
+pub mod ws;
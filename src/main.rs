@@ -1,30 +1,34 @@
-pub mod counter;
-pub mod util;
-pub mod ws;
-
-use axum::{
-    body::Body,
-    error_handling::HandleErrorLayer,
-    extract::{Path, State},
-    http::{HeaderValue, Request, StatusCode},
-    response::IntoResponse,
-    routing::{any, get},
-    Router,
-};
 use axum_server::tls_rustls::RustlsConfig;
 use clap::Parser;
-use counter::Counter;
-use std::{net::TcpListener, path::PathBuf};
-use tokio::{sync::broadcast, time::Duration};
-use tower::{BoxError, ServiceBuilder, ServiceExt};
-use tower_http::{
-    compression::CompressionLayer,
-    cors::{Any, CorsLayer},
-    services::{ServeDir, ServeFile},
-    trace::TraceLayer,
+use futures::FutureExt;
+#[cfg(feature = "acme")]
+use one_googol::acme::{self, AcmeConfig};
+use one_googol::{
+    account::{AccountStore, Accounts},
+    app::{build_app, AppConfig},
+    bot::{BotConfig, BotPolicy},
+    challenge::{ChallengeConfig, Challenger},
+    contribution::Contributions,
+    counter::Counter,
+    engine::{EngineConfig, GameEngine},
+    milestone::Milestones,
+    net::{redirect_app, ProxyConfig},
+    poll::{PollRules, TieBreak, VoteWeighting},
+    schedule::{Schedule, ScheduleStore},
+    season::HallOfFame,
+    security::{self, AllowedOrigins, SecurityConfig},
+    tls::spawn_cert_reloader,
+    upgrade::Catalogue,
+    util,
+    ws::{spawn_presence, spawn_schedule, spawn_updater, WebSocketState},
+};
+use std::{
+    net::{SocketAddr, TcpListener},
+    path::PathBuf,
+    sync::Arc,
 };
+use tokio::{sync::broadcast, time::Duration};
 use tracing::{error, info};
-use ws::{spawn_updater, ws_handler, WebSocketState};
 
 /// Command-line arguments structure using Clap
 #[derive(Parser)]
@@ -33,7 +37,7 @@ struct Args {
     /// Host in the format ip:port
     host: String,
 
-    /// Path to the view folder
+    /// Path to the view folder, ignored if the frontend is embedded with the `embed` feature
     #[arg(short, default_value = "view")]
     view: PathBuf,
 
@@ -41,6 +45,62 @@ struct Args {
     #[arg(short, default_value = "counter.json")]
     counter: PathBuf,
 
+    /// Path to the hall of fame file where completed games are archived
+    #[arg(long, default_value = "hall_of_fame.json")]
+    hall_of_fame: PathBuf,
+
+    /// Path to the persistent contributions file
+    #[arg(long, default_value = "contributions.json")]
+    contributions: PathBuf,
+
+    /// Path to the persistent accounts file
+    #[arg(long, default_value = "accounts.json")]
+    accounts: PathBuf,
+
+    /// Seconds after reaching One Googol until a new season starts, never if unset
+    #[arg(long)]
+    new_season_after: Option<u64>,
+
+    /// Fraction of the players which must vote or abstain before a poll ends
+    #[arg(long, default_value_t = 0.0)]
+    poll_quorum: f64,
+
+    /// Winner of a poll ending in a tie
+    #[arg(long, value_enum, default_value_t = TieBreak::Extend)]
+    poll_tie_break: TieBreak,
+
+    /// How much the vote of a player counts
+    #[arg(long, value_enum, default_value_t = VoteWeighting::Equal)]
+    poll_weighting: VoteWeighting,
+
+    /// Upper bound of the votes of a single player with contribution weighting
+    #[arg(long, default_value_t = 10)]
+    poll_max_weight: u32,
+
+    /// Seconds a poll runs at least
+    #[arg(long, default_value_t = 0)]
+    poll_min_runtime: u64,
+
+    /// Upgrades offered per poll
+    #[arg(long, default_value_t = 2)]
+    poll_options: usize,
+
+    /// Path to the milestones of a season, the built-in ones if unset
+    #[arg(long)]
+    milestones: Option<PathBuf>,
+
+    /// Splits the players into incrementers and decrementers with their own upgrades and polls
+    #[arg(long)]
+    team_mode: bool,
+
+    /// Path to the file with the timed events
+    #[arg(long, default_value = "schedule.json")]
+    schedule: PathBuf,
+
+    /// Path to the catalogue of upgrades polls offer, only base and exponent if unset
+    #[arg(long)]
+    upgrades: Option<PathBuf>,
+
     /// Path to the SSL certificate
     #[arg(
         long,
@@ -54,6 +114,85 @@ struct Args {
         default_value = "/etc/letsencrypt/live/one-googol.nwrenger.dev/privkey.pem"
     )]
     key: PathBuf,
+
+    /// Seconds between checks of the cert and key files for renewals, `0` only reloads on SIGHUP
+    #[arg(long, default_value_t = 60)]
+    cert_check_interval: u64,
+
+    /// Serve plain http without tls, e.g. behind a tls terminating reverse proxy
+    #[arg(long)]
+    no_tls: bool,
+
+    /// Additional address in the format ip:port whose requests are redirected to https
+    #[arg(long)]
+    redirect_http: Option<String>,
+
+    /// Trust the `X-Forwarded-For` and `X-Forwarded-Proto` headers of a reverse proxy
+    #[arg(long)]
+    trust_proxy: bool,
+
+    /// Obtain and renew the certificate for this domain via acme instead of `--cert` and `--key`,
+    /// requires `--redirect-http` on port 80 for the http-01 challenges
+    #[cfg(feature = "acme")]
+    #[arg(long)]
+    acme_domain: Option<String>,
+
+    /// Contact email of the acme account
+    #[cfg(feature = "acme")]
+    #[arg(long)]
+    acme_email: Option<String>,
+
+    /// Directory url of the acme server
+    #[cfg(feature = "acme")]
+    #[arg(long, default_value = acme::LETS_ENCRYPT)]
+    acme_directory: String,
+
+    /// Directory where the acme account key and certificates are stored
+    #[cfg(feature = "acme")]
+    #[arg(long, default_value = "acme")]
+    acme_cache: PathBuf,
+
+    /// Additional trusted root certificate of the acme server, e.g. for testing with Pebble
+    #[cfg(feature = "acme")]
+    #[arg(long)]
+    acme_ca_root: Option<PathBuf>,
+
+    /// Seconds browsers should only use https (`Strict-Transport-Security`), `0` disables it
+    #[arg(long, default_value_t = 365 * 24 * 60 * 60)]
+    hsts_max_age: u64,
+
+    /// The `Content-Security-Policy` header
+    #[arg(long, default_value = security::DEFAULT_CSP)]
+    content_security_policy: String,
+
+    /// Origin in the format scheme://host[:port] allowed to use the api and websocket besides
+    /// our own, can be repeated, `*` allows any origin for development
+    #[arg(long = "allow-origin")]
+    allow_origins: Vec<String>,
+
+    /// How clients detected as bots are treated
+    #[arg(long, value_enum, default_value_t = BotPolicy::Monitor)]
+    bot_policy: BotPolicy,
+
+    /// Score between 0 and 1 from which a client is flagged as bot
+    #[arg(long, default_value_t = 0.8)]
+    bot_threshold: f64,
+
+    /// Bearer token for the admin api, e.g. `GET /api/admin/flagged`, disabled if unset
+    #[arg(long, env = "ONE_GOOGOL_ADMIN_TOKEN")]
+    admin_token: Option<String>,
+
+    /// Leading zero bits of proof-of-work required to join, joins are not gated if unset
+    #[arg(long)]
+    challenge_difficulty: Option<u32>,
+
+    /// Connected clients from which every doubling raises the difficulty by one bit
+    #[arg(long, default_value_t = 500)]
+    challenge_load_threshold: usize,
+
+    /// Upper bound of the load adapted difficulty, browsers need a few seconds for 20 bits
+    #[arg(long, default_value_t = 20)]
+    challenge_max_difficulty: u32,
 }
 
 #[tokio::main]
@@ -62,71 +201,209 @@ async fn main() {
 
     let args = Args::parse();
 
-    if !PathBuf::from(&args.view).exists() {
+    if !cfg!(feature = "embed") && !PathBuf::from(&args.view).exists() {
         error!("The path for view content {:?} is invalid!", args.view);
         std::process::exit(1);
     }
 
-    if !PathBuf::from(&args.cert).exists() {
+    #[cfg(feature = "acme")]
+    let acme = args.acme_domain.clone().map(|domain| AcmeConfig {
+        domain,
+        email: args.acme_email.clone(),
+        directory: args.acme_directory.clone(),
+        cache: args.acme_cache.clone(),
+        ca_root: args.acme_ca_root.clone(),
+    });
+
+    #[cfg(feature = "acme")]
+    if acme.is_some() && (args.no_tls || args.redirect_http.is_none()) {
+        error!("Acme requires tls and --redirect-http to answer challenges!");
+        std::process::exit(1);
+    }
+
+    #[cfg(feature = "acme")]
+    let manual_tls = !args.no_tls && acme.is_none();
+    #[cfg(not(feature = "acme"))]
+    let manual_tls = !args.no_tls;
+
+    if manual_tls && !PathBuf::from(&args.cert).exists() {
         error!("The SSL certificate path {:?} does not exist!", args.cert);
         std::process::exit(1);
     }
 
-    if !PathBuf::from(&args.key).exists() {
+    if manual_tls && !PathBuf::from(&args.key).exists() {
         error!("The SSL key path {:?} does not exist!", args.key);
         std::process::exit(1);
     }
 
-    let mut counter = Counter::new();
+    let Some(mut security) = SecurityConfig::new(args.hsts_max_age, &args.content_security_policy)
+    else {
+        error!("The Content-Security-Policy is not a valid header value!");
+        std::process::exit(1);
+    };
+
+    security.allowed_origins = match AllowedOrigins::new(&args.allow_origins) {
+        Ok(origins) => origins,
+        Err(origin) => {
+            error!(
+                "The origin {:?} is invalid, expected scheme://host[:port]!",
+                origin
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let mut counter = Counter::new(util::now_secs());
     counter.load_from_file(&args.counter);
 
+    let mut hall_of_fame = HallOfFame::default();
+    hall_of_fame.load_from_file(&args.hall_of_fame);
+
+    let mut contributions = Contributions::default();
+    contributions.load_from_file(&args.contributions);
+
+    let mut accounts = Accounts::default();
+    accounts.load_from_file(&args.accounts);
+
+    let mut milestones = Milestones::default();
+    if let Some(path) = &args.milestones {
+        milestones.load_from_file(path);
+    }
+
+    let mut schedule = Schedule::default();
+    schedule.load_from_file(&args.schedule);
+
+    let mut upgrades = Catalogue::default();
+    if let Some(path) = &args.upgrades {
+        upgrades.load_from_file(path);
+    }
+    let poll_options = args.poll_options.max(2);
+    if let Err(e) = upgrades.validate(poll_options) {
+        error!("The upgrade catalogue is invalid: {}!", e);
+        std::process::exit(1);
+    }
+
     let (sender, _) = broadcast::channel(100);
 
-    let ws_state = WebSocketState::new(counter, sender);
-
-    spawn_updater(ws_state.clone());
-
-    let app = Router::new()
-        .route("/ws", any(ws_handler).with_state(ws_state.clone()))
-        .route("/", get(static_index).with_state(PathBuf::from(&args.view)))
-        .route(
-            "/{*file}",
-            get(static_assets).with_state(PathBuf::from(&args.view)),
-        )
-        .layer(
-            ServiceBuilder::new()
-                .layer(
-                    CorsLayer::new()
-                        .allow_origin(args.host.parse::<HeaderValue>().unwrap())
-                        .allow_methods(Any),
-                )
-                .layer(CompressionLayer::new())
-                .layer(HandleErrorLayer::new(|error: BoxError| async move {
-                    if error.is::<tower::timeout::error::Elapsed>() {
-                        Ok(StatusCode::REQUEST_TIMEOUT)
-                    } else {
-                        error!("Internal server error: {error}");
-                        Err(StatusCode::INTERNAL_SERVER_ERROR)
-                    }
+    let engine = GameEngine::new(
+        counter,
+        EngineConfig {
+            new_season_after: args.new_season_after.map(Duration::from_secs),
+            poll: PollRules {
+                quorum: args.poll_quorum.clamp(0.0, 1.0),
+                tie_break: args.poll_tie_break,
+                weighting: args.poll_weighting,
+                max_weight: args.poll_max_weight,
+                min_runtime: Duration::from_secs(args.poll_min_runtime),
+                options: poll_options,
+            },
+            upgrades,
+            milestones,
+            teams: args.team_mode,
+            ..Default::default()
+        },
+    );
+
+    let bot = BotConfig {
+        policy: args.bot_policy,
+        threshold: args.bot_threshold,
+    };
+
+    let schedule = Arc::new(ScheduleStore::new(schedule, args.schedule.clone()));
+    let ws_state = WebSocketState::new(engine, hall_of_fame, contributions, bot, schedule, sender);
+
+    spawn_updater(ws_state.clone(), args.hall_of_fame.clone());
+    spawn_presence(ws_state.clone());
+    spawn_schedule(ws_state.clone());
+
+    let app = build_app(
+        &AppConfig {
+            view: args.view.clone(),
+            proxy: ProxyConfig {
+                tls: !args.no_tls,
+                trust_proxy: args.trust_proxy,
+            },
+            security,
+            admin_token: args.admin_token.as_deref().map(Arc::from),
+            accounts: Arc::new(AccountStore::new(accounts, args.accounts.clone())),
+            challenge: args.challenge_difficulty.map(|difficulty| {
+                Arc::new(Challenger::new(ChallengeConfig {
+                    difficulty,
+                    load_threshold: args.challenge_load_threshold,
+                    max_difficulty: args.challenge_max_difficulty.max(difficulty),
+                    ttl: 120,
                 }))
-                .timeout(Duration::from_secs(10))
-                .layer(TraceLayer::new_for_http())
-                .into_inner(),
-        );
+            }),
+        },
+        ws_state.clone(),
+    );
 
     let handle = axum_server::Handle::new();
     let shut = util::shutdown_signal();
 
     let tcp = TcpListener::bind(&args.host).unwrap();
-    let tls = RustlsConfig::from_pem_file(&args.cert, &args.key)
-        .await
-        .unwrap();
+    let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
 
-    info!("Server started on \"{}\"", args.host);
+    #[cfg(feature = "acme")]
+    let challenges = acme::Challenges::default();
+    #[cfg(feature = "acme")]
+    let acme_tls = match acme {
+        Some(acme) => {
+            let tls = acme::initial_config(&acme).await;
+            acme::spawn_acme(acme, tls.clone(), challenges.clone());
+            Some(tls)
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "acme"))]
+    let acme_tls: Option<RustlsConfig> = None;
 
-    let server = axum_server::from_tcp_rustls(tcp, tls)
-        .handle(handle.clone())
-        .serve(app.into_make_service());
+    let server = if args.no_tls {
+        info!("Server started on \"{}\" without tls", args.host);
+        axum_server::from_tcp(tcp)
+            .handle(handle.clone())
+            .serve(make_service)
+            .boxed()
+    } else {
+        let tls = match acme_tls {
+            Some(tls) => tls,
+            None => {
+                let tls = RustlsConfig::from_pem_file(&args.cert, &args.key)
+                    .await
+                    .unwrap();
+                spawn_cert_reloader(
+                    tls.clone(),
+                    args.cert.clone(),
+                    args.key.clone(),
+                    (args.cert_check_interval > 0)
+                        .then(|| Duration::from_secs(args.cert_check_interval)),
+                );
+                tls
+            }
+        };
+        info!("Server started on \"{}\"", args.host);
+        axum_server::from_tcp_rustls(tcp, tls)
+            .handle(handle.clone())
+            .serve(make_service)
+            .boxed()
+    };
+
+    if let Some(redirect_host) = &args.redirect_http {
+        let https_port = tcp_port(&args.host);
+        let tcp = TcpListener::bind(redirect_host).unwrap();
+        info!("Redirecting \"{}\" to https", redirect_host);
+        let redirect = redirect_app(https_port);
+        #[cfg(feature = "acme")]
+        let redirect = acme::challenge_app(challenges).merge(redirect);
+        let redirect = axum_server::from_tcp(tcp)
+            .handle(handle.clone())
+            .serve(redirect.into_make_service());
+        tokio::spawn(async move {
+            if let Err(e) = redirect.await {
+                error!("Redirect server failed: {}", e);
+            }
+        });
+    }
 
     tokio::select! {
         () = shut =>
@@ -134,7 +411,8 @@ async fn main() {
         res = server => res.unwrap(),
     }
 
-    let counter = ws_state.counter.read().await;
+    // Saved from snapshots, the locks are not held while writing
+    let counter = ws_state.engine.read().await.counter.clone();
     if let Err(e) = counter.save_to_file(&args.counter) {
         error!("Error saving \"{:?}\" to file: {}", counter, e);
     } else {
@@ -143,32 +421,27 @@ async fn main() {
             counter, &args.counter
         );
     }
-}
-
-async fn static_index(State(path): State<PathBuf>, req: Request<Body>) -> impl IntoResponse {
-    ServeFile::new(path.join("index.html"))
-        .oneshot(req)
-        .await
-        .unwrap()
-        .into_response()
-}
 
-async fn static_assets(
-    State(path): State<PathBuf>,
-    Path(file): Path<String>,
-    req: Request<Body>,
-) -> impl IntoResponse {
-    if !file.contains('.') {
-        ServeFile::new(path.join(file).with_extension("html"))
-            .oneshot(req)
-            .await
-            .unwrap()
-            .into_response()
+    let contributions = ws_state.contributions.read().await.clone();
+    if let Err(e) = contributions.save_to_file(&args.contributions) {
+        error!("Error saving contributions to file: {}", e);
     } else {
-        ServeDir::new(path.clone())
-            .oneshot(req)
-            .await
-            .unwrap()
-            .into_response()
+        info!(
+            "Contributions saved successfully to {:?}",
+            &args.contributions
+        );
     }
 }
+
+/// Returns the port of a host in the format ip:port
+fn tcp_port(host: &str) -> u16 {
+    host.parse::<SocketAddr>()
+        .map(|addr| addr.port())
+        .or_else(|_| {
+            host.rsplit_once(':')
+                .map(|(_, port)| port)
+                .unwrap_or("")
+                .parse()
+        })
+        .unwrap_or(443)
+}
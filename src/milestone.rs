@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::BufReader, path::PathBuf};
+use tracing::info;
+
+/// When a milestone is reached
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Condition {
+    /// The count has at least this many digits
+    Digits { digits: usize },
+    /// At least this many players are connected at once
+    Players { players: usize },
+    /// The count got back to its highest digit length after losing at least this many digits
+    Comeback { digits: usize },
+}
+
+/// A named goal of a season
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Milestone {
+    /// Unique id, each milestone is reached once per season
+    pub id: String,
+    pub name: String,
+    pub condition: Condition,
+}
+
+/// All milestones of a season
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Milestones {
+    pub milestones: Vec<Milestone>,
+}
+
+impl Default for Milestones {
+    fn default() -> Self {
+        let milestone = |id: &str, name: &str, condition| Milestone {
+            id: id.into(),
+            name: name.into(),
+            condition,
+        };
+        Self {
+            milestones: vec![
+                milestone("1e25", "First 1e25", Condition::Digits { digits: 26 }),
+                milestone("1e50", "First 1e50", Condition::Digits { digits: 51 }),
+                milestone("1e75", "First 1e75", Condition::Digits { digits: 76 }),
+                milestone("crowd", "100 Players", Condition::Players { players: 100 }),
+                milestone("comeback", "Comeback", Condition::Comeback { digits: 5 }),
+            ],
+        }
+    }
+}
+
+impl Milestones {
+    /// Loads the milestones from a json file
+    pub fn load_from_file(&mut self, path: &PathBuf) {
+        let file = File::open(path).unwrap();
+        let reader = BufReader::new(file);
+        *self = serde_json::from_reader(reader).unwrap();
+        info!("Loaded Milestones from file: {:?}", path);
+    }
+
+    /// Records the milestones reached by the current state and returns them
+    pub fn check(
+        &self,
+        progress: &mut Progress,
+        digits: usize,
+        players: usize,
+        now: u64,
+        tick: u64,
+    ) -> Vec<Reached> {
+        // Digits lost since the highest length, if that length was regained
+        let mut comeback = 0;
+        if digits >= progress.peak_digits {
+            comeback = progress.peak_digits - progress.low_digits;
+            progress.peak_digits = digits;
+            progress.low_digits = digits;
+        } else {
+            progress.low_digits = progress.low_digits.min(digits);
+        }
+
+        let mut reached = vec![];
+        for milestone in &self.milestones {
+            let met = match milestone.condition {
+                Condition::Digits { digits: goal } => digits >= goal,
+                Condition::Players { players: goal } => players >= goal,
+                Condition::Comeback { digits: goal } => comeback >= goal,
+            };
+            if met && !progress.is_reached(&milestone.id) {
+                reached.push(Reached {
+                    id: milestone.id.clone(),
+                    name: milestone.name.clone(),
+                    reached_at: now,
+                    tick,
+                });
+            }
+        }
+        progress.reached.extend(reached.iter().cloned());
+        reached
+    }
+}
+
+/// A reached milestone
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reached {
+    pub id: String,
+    pub name: String,
+    /// Unix time in seconds
+    pub reached_at: u64,
+    /// Tick of the season
+    pub tick: u64,
+}
+
+/// Milestones of the running season
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    pub reached: Vec<Reached>,
+    /// Highest digit length so far
+    pub peak_digits: usize,
+    /// Lowest digit length since the highest was reached
+    pub low_digits: usize,
+}
+
+impl Progress {
+    /// Returns true if the milestone was reached this season
+    pub fn is_reached(&self, id: &str) -> bool {
+        self.reached.iter().any(|reached| reached.id == id)
+    }
+}
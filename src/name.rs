@@ -0,0 +1,75 @@
+use std::fmt;
+
+/// Maximum number of characters of a nickname
+pub const MAX_NAME_LENGTH: usize = 24;
+
+/// Words which may not appear in nicknames, matched after normalization.
+///
+/// Kept to unambiguous stems so regular words like "grape" or "analysis" pass.
+const BLOCKED_WORDS: &[&str] = &[
+    "admin", "asshole", "bastard", "bitch", "blowjob", "bollock", "cunt", "dildo", "faggot",
+    "fuck", "hitler", "jizz", "nazi", "nigg", "penis", "porn", "pussy", "retard", "shit", "slut",
+    "twat", "vagina", "wank", "whore",
+];
+
+/// Reasons a nickname is rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    Empty,
+    TooLong,
+    InvalidCharacter(char),
+    Profanity,
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "name is empty"),
+            Self::TooLong => write!(f, "name is longer than {MAX_NAME_LENGTH} characters"),
+            Self::InvalidCharacter(c) => write!(f, "name contains invalid character {c:?}"),
+            Self::Profanity => write!(f, "name contains a blocked word"),
+        }
+    }
+}
+
+/// Validates a nickname and returns it trimmed
+///
+/// Names may contain letters, digits, spaces, `-`, `_` and `.`.
+pub fn validate(name: &str) -> Result<String, NameError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(NameError::TooLong);
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.')))
+    {
+        return Err(NameError::InvalidCharacter(c));
+    }
+    if is_profane(name) {
+        return Err(NameError::Profanity);
+    }
+    Ok(name.to_string())
+}
+
+/// Checks for blocked words, ignoring case, separators and common letter substitutions
+fn is_profane(name: &str) -> bool {
+    let normalized: String = name
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .map(|c| match c.to_ascii_lowercase() {
+            '0' => 'o',
+            '1' => 'i',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            '8' => 'b',
+            c => c,
+        })
+        .collect();
+    BLOCKED_WORDS.iter().any(|word| normalized.contains(word))
+}
@@ -0,0 +1,102 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, uri::PathAndQuery, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+use std::net::{IpAddr, SocketAddr};
+
+/// How the server is reached
+#[derive(Debug, Clone, Copy)]
+pub struct ProxyConfig {
+    /// Whether the server itself terminates tls
+    pub tls: bool,
+    /// Whether `X-Forwarded-For` and `X-Forwarded-Proto` of a reverse proxy are honoured
+    pub trust_proxy: bool,
+}
+
+/// Remote address and scheme of a request, inserted as extension by [`client_info`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub ip: Option<IpAddr>,
+    /// True if the client connected via https
+    pub secure: bool,
+}
+
+impl ClientInfo {
+    /// Determines the client info from the connection and trusted proxy headers
+    pub fn new(config: ProxyConfig, peer: Option<IpAddr>, headers: &HeaderMap) -> Self {
+        let mut info = Self {
+            ip: peer,
+            secure: config.tls,
+        };
+        if !config.trust_proxy {
+            return info;
+        }
+
+        // The last entry was appended by our proxy, earlier ones can be forged by the client
+        if let Some(ip) = forwarded_value(headers, "x-forwarded-for").and_then(|v| v.parse().ok()) {
+            info.ip = Some(ip);
+        }
+        if let Some(proto) = forwarded_value(headers, "x-forwarded-proto") {
+            info.secure = proto.eq_ignore_ascii_case("https");
+        }
+        info
+    }
+}
+
+/// Returns the last comma separated value of a header
+fn forwarded_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .rfind(|value| !value.is_empty())
+}
+
+/// Middleware inserting the [`ClientInfo`] of every request
+pub async fn client_info(
+    State(config): State<ProxyConfig>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let info = ClientInfo::new(config, peer, req.headers());
+    req.extensions_mut().insert(info);
+    next.run(req).await
+}
+
+/// Builds a router which redirects every request to https on `https_port`
+pub fn redirect_app(https_port: u16) -> Router {
+    Router::new().fallback(move |req: Request| async move { redirect(&req, https_port) })
+}
+
+/// Permanently redirects a request to its https equivalent
+fn redirect(req: &Request, https_port: u16) -> Response {
+    let Some(host) = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+    else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    // Strip the port, keeping bracketed ipv6 addresses intact
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    let path = req.uri().path_and_query().map_or("/", PathAndQuery::as_str);
+
+    let location = if https_port == 443 {
+        format!("https://{host}{path}")
+    } else {
+        format!("https://{host}:{https_port}{path}")
+    };
+    Redirect::permanent(&location).into_response()
+}
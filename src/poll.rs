@@ -0,0 +1,146 @@
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{
+    counter::{PollMeter, PollOption},
+    ws::CounterState,
+};
+
+/// How a poll whose leading options received the same votes is decided
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TieBreak {
+    /// The countdown pauses while the leading options are tied, like polls always did. A
+    /// poll tied at zero, e.g. after all players left, goes to the last tied option.
+    #[default]
+    Extend,
+    /// The last tied option wins, the exponent in the classic catalogue
+    #[value(alias = "exponent")]
+    Last,
+    /// The first tied option wins, the base in the classic catalogue
+    #[value(alias = "base")]
+    First,
+    /// The previous upgrade is repeated if tied, else the last tied option wins
+    Repeat,
+    /// The last tied option other than the previous upgrade wins
+    Alternate,
+}
+
+/// How much a single vote counts
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum VoteWeighting {
+    /// Every player has one vote
+    #[default]
+    Equal,
+    /// Every order of magnitude a player added grants another vote
+    Contribution,
+}
+
+/// Rules deciding when a poll ends and who wins
+#[derive(Debug, Clone)]
+pub struct PollRules {
+    /// Fraction of the players which must have voted or abstained before a poll ends
+    pub quorum: f64,
+    pub tie_break: TieBreak,
+    pub weighting: VoteWeighting,
+    /// Upper bound of the votes of a single player
+    pub max_weight: u32,
+    /// Time a poll runs at least, even if everybody voted early
+    pub min_runtime: Duration,
+    /// Upgrades offered per poll
+    pub options: usize,
+}
+
+impl Default for PollRules {
+    fn default() -> Self {
+        Self {
+            quorum: 0.0,
+            tie_break: TieBreak::Extend,
+            weighting: VoteWeighting::Equal,
+            max_weight: 10,
+            min_runtime: Duration::ZERO,
+            options: 2,
+        }
+    }
+}
+
+impl PollRules {
+    /// Votes of a player who added `added` so far
+    pub fn weight(&self, added: &BigInt) -> u32 {
+        match self.weighting {
+            VoteWeighting::Equal => 1,
+            VoteWeighting::Contribution => {
+                let digits = if added > &BigInt::ZERO {
+                    added.to_str_radix(10).len() as u32
+                } else {
+                    0
+                };
+                (1 + digits).min(self.max_weight.max(1))
+            }
+        }
+    }
+
+    /// Returns true if enough votes were cast, abstentions included
+    pub fn quorum_met(&self, meter: &PollMeter) -> bool {
+        if self.quorum <= 0.0 {
+            return true;
+        }
+        let voted = meter.voted();
+        let total = voted + meter.pending;
+        total > 0 && voted as f64 >= self.quorum * total as f64
+    }
+
+    /// Picks the winner of a finished poll, `last` is the previous upgrade.
+    /// Returns the id of the winner and whether the tie-break decided.
+    pub fn winner(
+        &self,
+        meter: &PollMeter,
+        options: &[PollOption],
+        last: Option<&str>,
+    ) -> Option<(String, bool)> {
+        let tied = meter.leaders(options);
+        let last_tied = *tied.last()?;
+        if tied.len() == 1 {
+            return Some((last_tied.to_string(), false));
+        }
+        let winner = match self.tie_break {
+            TieBreak::First => tied[0],
+            TieBreak::Repeat => tied
+                .iter()
+                .find(|id| Some(**id) == last)
+                .unwrap_or(&last_tied),
+            TieBreak::Alternate => tied
+                .iter()
+                .rev()
+                .find(|id| Some(**id) != last)
+                .unwrap_or(&last_tied),
+            TieBreak::Last | TieBreak::Extend => last_tied,
+        };
+        Some((winner.to_string(), true))
+    }
+}
+
+/// Outcome of a finished poll with its full tally
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PollResult {
+    /// Id of the applied upgrade
+    pub winner: String,
+    /// Final weighted votes
+    pub tally: PollMeter,
+    pub amplification: u32,
+    /// Ticks the poll ran
+    pub ticks: u32,
+    /// Time the poll ran
+    pub duration_ms: u64,
+    /// True if the tie-break policy decided
+    pub tie_broken: bool,
+    /// Upgrade level which started the poll
+    pub level: usize,
+    /// Base after the upgrade was applied
+    pub base: u32,
+    /// Exponent after the upgrade was applied
+    pub exponent: u32,
+    /// Team which voted, in team mode
+    #[serde(default)]
+    pub team: Option<CounterState>,
+}
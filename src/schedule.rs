@@ -0,0 +1,201 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+    sync::Arc,
+};
+use tokio::sync::RwLock;
+use tracing::{error, info};
+
+use crate::util;
+
+/// Percentages applied to a counter update, 100 leaves it unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Modifier {
+    /// Percentage of the increment and decrement step
+    #[serde(default = "Modifier::unchanged")]
+    pub step: u32,
+    /// Percentage of the action modifier
+    #[serde(default = "Modifier::unchanged")]
+    pub action: u32,
+    /// Percentage of everything removed by decrementers
+    #[serde(default = "Modifier::unchanged")]
+    pub decrement: u32,
+}
+
+impl Default for Modifier {
+    fn default() -> Self {
+        Self {
+            step: 100,
+            action: 100,
+            decrement: 100,
+        }
+    }
+}
+
+impl Modifier {
+    fn unchanged() -> u32 {
+        100
+    }
+
+    /// Applies both modifiers after another
+    pub fn combine(self, other: Self) -> Self {
+        let combine = |a: u32, b: u32| (a as u64 * b as u64 / 100).min(u32::MAX as u64) as u32;
+        Self {
+            step: combine(self.step, other.step),
+            action: combine(self.action, other.action),
+            decrement: combine(self.decrement, other.decrement),
+        }
+    }
+}
+
+/// A community event modifying the counter for a while, e.g. a double-step weekend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledEvent {
+    /// Unique id, events with the same id replace each other
+    pub id: String,
+    pub name: String,
+    /// Unix time in seconds the event starts
+    pub starts_at: u64,
+    /// Unix time in seconds the event ends
+    pub ends_at: u64,
+    pub modifier: Modifier,
+}
+
+impl ScheduledEvent {
+    /// Returns true if the event runs at the unix time `now`
+    pub fn is_active(&self, now: u64) -> bool {
+        (self.starts_at..self.ends_at).contains(&now)
+    }
+}
+
+/// A running event as broadcast to the clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActiveEvent {
+    pub id: String,
+    pub name: String,
+    pub modifier: Modifier,
+    pub ends_at: u64,
+    pub remaining_secs: u64,
+}
+
+/// All past, running and upcoming events
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schedule {
+    pub events: Vec<ScheduledEvent>,
+}
+
+impl Schedule {
+    /// Loads the schedule from a json file if it exists
+    pub fn load_from_file(&mut self, path: &PathBuf) {
+        if path.exists() {
+            let file = File::open(path).unwrap();
+            let reader = BufReader::new(file);
+            *self = serde_json::from_reader(reader).unwrap();
+            info!("Loaded Schedule from file: {:?}", path);
+        }
+    }
+
+    /// Saves the schedule to a json file
+    pub fn save_to_file(&self, path: &PathBuf) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Events running at the unix time `now`
+    pub fn active(&self, now: u64) -> Vec<ActiveEvent> {
+        self.events
+            .iter()
+            .filter(|event| event.is_active(now))
+            .map(|event| ActiveEvent {
+                id: event.id.clone(),
+                name: event.name.clone(),
+                modifier: event.modifier,
+                ends_at: event.ends_at,
+                remaining_secs: event.ends_at - now,
+            })
+            .collect()
+    }
+
+    /// Combined modifier of the events running at the unix time `now`
+    pub fn modifier(&self, now: u64) -> Modifier {
+        self.events
+            .iter()
+            .filter(|event| event.is_active(now))
+            .fold(Modifier::default(), |modifier, event| {
+                modifier.combine(event.modifier)
+            })
+    }
+}
+
+/// The schedule with its persistence
+#[derive(Debug)]
+pub struct ScheduleStore {
+    pub schedule: RwLock<Schedule>,
+    path: PathBuf,
+}
+
+impl ScheduleStore {
+    pub fn new(schedule: Schedule, path: PathBuf) -> Self {
+        Self {
+            schedule: RwLock::new(schedule),
+            path,
+        }
+    }
+
+    /// Saves the schedule, logging errors
+    fn save(&self, schedule: &Schedule) {
+        if let Err(e) = schedule.save_to_file(&self.path) {
+            error!("Error saving schedule: {}", e);
+        }
+    }
+}
+
+/// Handler for `GET /api/admin/events`, lists all scheduled events
+pub async fn list(State(store): State<Arc<ScheduleStore>>) -> impl IntoResponse {
+    Json(store.schedule.read().await.events.clone())
+}
+
+/// Handler for `POST /api/admin/events`, schedules an event or replaces the one with the same id.
+/// Expired events are dropped.
+pub async fn schedule(
+    State(store): State<Arc<ScheduleStore>>,
+    Json(event): Json<ScheduledEvent>,
+) -> Response {
+    if event.id.is_empty() || event.ends_at <= event.starts_at {
+        let message = "events need an id and must end after they start";
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    let now = util::now_secs();
+    let mut schedule = store.schedule.write().await;
+    schedule
+        .events
+        .retain(|e| e.id != event.id && e.ends_at > now);
+    info!("Scheduled event {:?}", event);
+    schedule.events.push(event.clone());
+    schedule.events.sort_by_key(|e| e.starts_at);
+    store.save(&schedule);
+    (StatusCode::CREATED, Json(event)).into_response()
+}
+
+/// Handler for `DELETE /api/admin/events/{id}`
+pub async fn cancel(State(store): State<Arc<ScheduleStore>>, Path(id): Path<String>) -> Response {
+    let mut schedule = store.schedule.write().await;
+    let before = schedule.events.len();
+    schedule.events.retain(|event| event.id != id);
+    if schedule.events.len() == before {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    info!("Cancelled event {}", id);
+    store.save(&schedule);
+    StatusCode::NO_CONTENT.into_response()
+}
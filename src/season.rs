@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use tracing::info;
+
+use crate::{
+    counter::{Counter, Upgrade},
+    poll::PollResult,
+    team::Teams,
+    util,
+};
+
+/// Progress of the currently running season.
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct Season {
+    pub number: u32,
+    pub started_at: u64,
+    pub completed_at: Option<u64>,
+    pub peak_players: usize,
+    /// Ids of the applied upgrades, in team mode each team keeps its own
+    #[serde(deserialize_with = "util::deserialize_upgrade_path")]
+    pub upgrade_path: Vec<String>,
+    #[serde(default)]
+    pub ticks: u64,
+    #[serde(default)]
+    pub polls: u32,
+}
+
+impl Default for Season {
+    /// The first season, for counters saved before seasons existed, starting when loaded
+    fn default() -> Self {
+        Self::new(1, util::now_secs())
+    }
+}
+
+impl Season {
+    /// Starts a new season with the given number
+    pub fn new(number: u32, started_at: u64) -> Self {
+        Self {
+            number,
+            started_at,
+            completed_at: None,
+            peak_players: 0,
+            upgrade_path: Vec::new(),
+            ticks: 0,
+            polls: 0,
+        }
+    }
+}
+
+/// A finished game as it is announced and archived in the hall of fame.
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct Completion {
+    pub season: u32,
+    pub started_at: u64,
+    pub completed_at: u64,
+    pub duration_secs: u64,
+    pub peak_players: usize,
+    /// Ids of the applied upgrades
+    #[serde(deserialize_with = "util::deserialize_upgrade_path")]
+    pub upgrade_path: Vec<String>,
+    #[serde(default)]
+    pub ticks: u64,
+    #[serde(default)]
+    pub polls: u32,
+    #[serde(default)]
+    pub upgrade: Upgrade,
+    /// Final upgrades and totals of both teams, in team mode
+    #[serde(default)]
+    pub teams: Option<Box<Teams>>,
+    /// Finished polls of the season, oldest first
+    #[serde(default)]
+    pub poll_history: Vec<PollResult>,
+}
+
+impl Completion {
+    /// Creates the completion record of the season of `counter` finished at `completed_at`
+    pub fn new(counter: &Counter, completed_at: u64) -> Self {
+        let season = &counter.season;
+        Self {
+            season: season.number,
+            started_at: season.started_at,
+            completed_at,
+            duration_secs: completed_at.saturating_sub(season.started_at),
+            peak_players: season.peak_players,
+            upgrade_path: season.upgrade_path.clone(),
+            ticks: season.ticks,
+            polls: season.polls,
+            upgrade: counter.upgrade.clone(),
+            teams: counter.teams.clone().map(Box::new),
+            poll_history: counter.poll_history.clone(),
+        }
+    }
+}
+
+/// Archive of all completed games.
+#[derive(Debug, Default, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct HallOfFame {
+    pub completions: Vec<Completion>,
+}
+
+impl HallOfFame {
+    /// Loads the hall of fame from a json file if it exists
+    pub fn load_from_file(&mut self, path: &PathBuf) {
+        if path.exists() {
+            let file = File::open(path).unwrap();
+            let reader = BufReader::new(file);
+            *self = serde_json::from_reader(reader).unwrap();
+            info!("Loaded Hall of Fame from file: {:?}", path);
+        }
+    }
+
+    /// Completed seasons ordered from the fastest to the slowest run
+    pub fn leaderboard(&self) -> Vec<Completion> {
+        let mut completions = self.completions.clone();
+        completions.sort_by_key(|c| (c.duration_secs, c.ticks, c.season));
+        completions
+    }
+
+    /// Saves the hall of fame to a json file
+    pub fn save_to_file(&self, path: &PathBuf) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Uri},
+    middleware::Next,
+    response::Response,
+};
+use tower_http::cors::{self, AllowOrigin, CorsLayer};
+
+use crate::net::ClientInfo;
+
+/// Default policy: everything from our own origin, the websocket and the inline scripts and styles
+/// of the prerendered SvelteKit pages. JetBrains Mono is served from `/JetBrainsMono.ttf`.
+pub const DEFAULT_CSP: &str = "default-src 'self'; connect-src 'self'; \
+    script-src 'self' 'unsafe-inline'; style-src 'self' 'unsafe-inline'; \
+    font-src 'self'; img-src 'self' data:; base-uri 'self'; form-action 'self'; \
+    frame-ancestors 'none'";
+
+/// Security headers added to every response
+#[derive(Debug, Clone)]
+pub struct SecurityConfig {
+    /// `Strict-Transport-Security`, only sent over https
+    pub hsts: Option<HeaderValue>,
+    /// `Content-Security-Policy`
+    pub content_security_policy: HeaderValue,
+    /// Origins allowed for cors and websocket upgrades
+    pub allowed_origins: AllowedOrigins,
+}
+
+impl SecurityConfig {
+    /// Creates the headers, a `hsts_max_age` of 0 disables HSTS
+    pub fn new(hsts_max_age: u64, content_security_policy: &str) -> Option<Self> {
+        Some(Self {
+            hsts: (hsts_max_age > 0)
+                .then(|| HeaderValue::try_from(format!("max-age={hsts_max_age}")).unwrap()),
+            content_security_policy: HeaderValue::try_from(content_security_policy).ok()?,
+            allowed_origins: AllowedOrigins::SameOrigin,
+        })
+    }
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self::new(365 * 24 * 60 * 60, DEFAULT_CSP).unwrap()
+    }
+}
+
+/// Middleware adding the security headers, requires the [`ClientInfo`] extension
+pub async fn security_headers(
+    State(config): State<SecurityConfig>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let secure = req
+        .extensions()
+        .get::<ClientInfo>()
+        .is_some_and(|info| info.secure);
+
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    if let (true, Some(hsts)) = (secure, &config.hsts) {
+        headers.insert(header::STRICT_TRANSPORT_SECURITY, hsts.clone());
+    }
+    headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        config.content_security_policy,
+    );
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+    headers.insert(
+        header::REFERRER_POLICY,
+        HeaderValue::from_static("strict-origin-when-cross-origin"),
+    );
+    response
+}
+
+/// Origins besides our own which may use the api and the websocket
+#[derive(Debug, Clone, Default)]
+pub enum AllowedOrigins {
+    /// Only the origin matching the requested host
+    #[default]
+    SameOrigin,
+    /// Additional origins in the format `scheme://host[:port]`
+    List(Vec<HeaderValue>),
+    /// Every origin, only meant for development
+    Any,
+}
+
+impl AllowedOrigins {
+    /// Parses the `--allow-origin` values, `*` allows any origin
+    pub fn new(origins: &[String]) -> Result<Self, String> {
+        if origins.iter().any(|origin| origin == "*") {
+            return Ok(Self::Any);
+        }
+        if origins.is_empty() {
+            return Ok(Self::SameOrigin);
+        }
+        origins
+            .iter()
+            .map(|origin| parse_origin(origin).ok_or_else(|| origin.clone()))
+            .collect::<Result<_, _>>()
+            .map(Self::List)
+    }
+
+    /// Whether the `Origin` of a request is allowed.
+    ///
+    /// Browsers always send the origin on websocket upgrades, other clients without one are allowed.
+    pub fn allows(&self, headers: &HeaderMap) -> bool {
+        let Some(origin) = headers.get(header::ORIGIN) else {
+            return true;
+        };
+        match self {
+            Self::Any => true,
+            Self::List(list) => list.contains(origin) || same_origin(headers),
+            Self::SameOrigin => same_origin(headers),
+        }
+    }
+
+    /// Cors layer allowing cross-origin api requests from these origins
+    pub fn cors(&self) -> CorsLayer {
+        let cors = CorsLayer::new().allow_methods(cors::Any);
+        match self {
+            // Same-origin requests need no cors headers
+            Self::SameOrigin => cors,
+            Self::List(list) => cors.allow_origin(AllowOrigin::list(list.iter().cloned())),
+            Self::Any => cors.allow_origin(cors::Any),
+        }
+    }
+}
+
+/// Normalizes an origin like `https://example.com`, which must not have a path
+fn parse_origin(origin: &str) -> Option<HeaderValue> {
+    let uri: Uri = origin.trim_end_matches('/').parse().ok()?;
+    let scheme = uri.scheme_str()?;
+    let authority = uri.authority()?;
+    if !matches!(scheme, "http" | "https") || uri.path().len() > 1 {
+        return None;
+    }
+    let origin = format!("{scheme}://{authority}").to_ascii_lowercase();
+    HeaderValue::try_from(origin).ok()
+}
+
+/// Whether the `Origin` of a request matches its `Host`
+fn same_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return false;
+    };
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+    let origin_host = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.split_once("://"))
+        .map(|(_, host)| host);
+    matches!((origin_host, host), (Some(origin), Some(host)) if origin.eq_ignore_ascii_case(host))
+}
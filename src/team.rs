@@ -0,0 +1,50 @@
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    counter::{Poll, Step, Upgrade},
+    util,
+    ws::CounterState,
+};
+
+/// One side of the tug-of-war with its own upgrades and polls
+#[derive(Debug, Default, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct Team {
+    pub upgrade: Upgrade,
+    pub poll: Option<Poll>,
+    /// Ids of the upgrades applied to the team this season
+    #[serde(default)]
+    pub upgrade_path: Vec<String>,
+    /// Players of the last tick
+    pub members: u32,
+    /// Amount added or removed by the team this season
+    #[serde(
+        serialize_with = "util::serialize_bigint",
+        deserialize_with = "util::deserialize_bigint"
+    )]
+    pub total: BigInt,
+}
+
+/// Incrementers against decrementers
+#[derive(Debug, Default, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct Teams {
+    pub increment: Team,
+    pub decrement: Team,
+    /// Side with the higher total, pending on a tie
+    pub leader: CounterState,
+}
+
+impl Teams {
+    /// Records the members and the step of a tick and updates the leader
+    pub fn record(&mut self, increment: u32, decrement: u32, step: &Step) {
+        self.increment.members = increment;
+        self.decrement.members = decrement;
+        self.increment.total += &step.increment + &step.increment_modifier;
+        self.decrement.total += &step.decrement + &step.decrement_modifier;
+        self.leader = match self.increment.total.cmp(&self.decrement.total) {
+            std::cmp::Ordering::Greater => CounterState::Increment,
+            std::cmp::Ordering::Less => CounterState::Decrement,
+            std::cmp::Ordering::Equal => CounterState::Pending,
+        };
+    }
+}
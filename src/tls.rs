@@ -0,0 +1,89 @@
+use axum_server::tls_rustls::RustlsConfig;
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tokio::time;
+use tracing::{error, info};
+
+/// Spawns a task which swaps the certificate of `config` in place once the cert or key file
+/// changes or on `SIGHUP`, keeping the old certificate if the new one fails to load.
+///
+/// The files are checked every `interval`, `None` only reloads on `SIGHUP`.
+pub fn spawn_cert_reloader(
+    config: RustlsConfig,
+    cert: PathBuf,
+    key: PathBuf,
+    interval: Option<Duration>,
+) {
+    tokio::spawn(async move {
+        let mut loaded = modified(&cert, &key);
+        let mut hangup = hangup_signal();
+        let mut interval = interval.map(time::interval);
+
+        loop {
+            let forced = tokio::select! {
+                _ = tick(&mut interval) => false,
+                _ = recv(&mut hangup) => true,
+            };
+
+            // Only reload changed files, a half renewed pair is retried once the other file changes
+            let current = modified(&cert, &key);
+            if !forced && current == loaded {
+                continue;
+            }
+            loaded = current;
+
+            match config.reload_from_pem_file(&cert, &key).await {
+                Ok(()) => info!("Reloaded tls certificate from {:?}", cert),
+                Err(e) => error!("Keeping the old tls certificate, reload failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Last modification times of the cert and key, following symlinks
+fn modified(cert: &Path, key: &Path) -> Option<(SystemTime, SystemTime)> {
+    let modified = |path: &Path| path.metadata().and_then(|m| m.modified()).ok();
+    Some((modified(cert)?, modified(key)?))
+}
+
+/// Waits for the next tick or forever without an interval
+async fn tick(interval: &mut Option<time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(unix)]
+type Hangup = Option<tokio::signal::unix::Signal>;
+#[cfg(not(unix))]
+type Hangup = Option<()>;
+
+/// Listens for `SIGHUP` if supported by the platform
+fn hangup_signal() -> Hangup {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        signal(SignalKind::hangup())
+            .map_err(|e| error!("Failed to listen for SIGHUP: {}", e))
+            .ok()
+    }
+    #[cfg(not(unix))]
+    None
+}
+
+/// Waits for the next `SIGHUP` or forever if not supported
+async fn recv(hangup: &mut Hangup) {
+    #[cfg(unix)]
+    if let Some(hangup) = hangup {
+        hangup.recv().await;
+        return;
+    }
+    #[cfg(not(unix))]
+    let _ = hangup;
+    std::future::pending().await
+}
@@ -0,0 +1,144 @@
+use rand::{rngs::StdRng, seq::index, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::BufReader, path::PathBuf};
+use tracing::info;
+
+use crate::counter::{Frenzy, PollOption, Upgrade};
+
+/// Highest decrement resistance in percent, sabotage always does something
+pub const MAX_DECREMENT_RESISTANCE: u32 = 90;
+
+/// What an upgrade does, amounts are multiplied by the amplification of the poll
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Effect {
+    /// Adds to the base of the step
+    Base { amount: u32 },
+    /// Adds to the exponent of the step
+    Exponent { amount: u32 },
+    /// Raises the action modifier by a percentage
+    ActionMultiplier { percent: u32 },
+    /// Lowers the decrements by a percentage, up to [`MAX_DECREMENT_RESISTANCE`], in team
+    /// mode the step of the other team
+    DecrementResistance { percent: u32 },
+    /// Applies the increment additional times per tick
+    TickBoost { steps: u32 },
+    /// Multiplies the increment for a number of seconds
+    Frenzy { multiplier: u32, secs: u64 },
+}
+
+impl Effect {
+    /// Applies the effect to the upgrades of a counter
+    pub fn apply(&self, upgrade: &mut Upgrade, amplification: u32) {
+        // Values come from the data file, large ones saturate instead of overflowing
+        let add =
+            |value: u32, amount: u32| value.saturating_add(amount.saturating_mul(amplification));
+        match *self {
+            Self::Base { amount } => upgrade.base = add(upgrade.base, amount),
+            Self::Exponent { amount } => upgrade.exponent = add(upgrade.exponent, amount),
+            Self::ActionMultiplier { percent } => {
+                upgrade.action_multiplier = add(upgrade.action_multiplier, percent)
+            }
+            Self::DecrementResistance { percent } => {
+                upgrade.decrement_resistance =
+                    add(upgrade.decrement_resistance, percent).min(MAX_DECREMENT_RESISTANCE)
+            }
+            Self::TickBoost { steps } => upgrade.tick_boost = add(upgrade.tick_boost, steps),
+            Self::Frenzy { multiplier, secs } => {
+                upgrade.frenzy = Some(Frenzy {
+                    multiplier,
+                    remaining_ms: secs
+                        .saturating_mul(amplification as u64)
+                        .saturating_mul(1000),
+                })
+            }
+        }
+    }
+}
+
+/// An upgrade which can be offered in polls
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpgradeKind {
+    /// Unique id, stored in polls and results
+    pub id: String,
+    /// Name shown to the players
+    pub label: String,
+    pub effect: Effect,
+}
+
+/// All upgrades polls draw their options from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Catalogue {
+    pub upgrades: Vec<UpgradeKind>,
+}
+
+impl Default for Catalogue {
+    /// The classic game, every poll is base against exponent
+    fn default() -> Self {
+        Self {
+            upgrades: vec![
+                UpgradeKind {
+                    id: "base".into(),
+                    label: "Base".into(),
+                    effect: Effect::Base { amount: 1 },
+                },
+                UpgradeKind {
+                    id: "exponent".into(),
+                    label: "Exponent".into(),
+                    effect: Effect::Exponent { amount: 1 },
+                },
+            ],
+        }
+    }
+}
+
+impl Catalogue {
+    /// Loads the catalogue from a json file
+    pub fn load_from_file(&mut self, path: &PathBuf) {
+        let file = File::open(path).unwrap();
+        let reader = BufReader::new(file);
+        *self = serde_json::from_reader(reader).unwrap();
+        info!("Loaded Upgrades from file: {:?}", path);
+    }
+
+    /// Returns an error message if the catalogue cannot fill polls of `options` upgrades
+    pub fn validate(&self, options: usize) -> Result<(), String> {
+        if self.upgrades.len() < options {
+            return Err(format!("at least {options} upgrades are required"));
+        }
+        for (i, upgrade) in self.upgrades.iter().enumerate() {
+            if self.upgrades[..i].iter().any(|u| u.id == upgrade.id) {
+                return Err(format!("the id {:?} is not unique", upgrade.id));
+            }
+            if let Effect::Frenzy { multiplier: 0, .. } = upgrade.effect {
+                return Err(format!("the frenzy {:?} has no multiplier", upgrade.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the upgrade with the given id
+    pub fn get(&self, id: &str) -> Option<&UpgradeKind> {
+        self.upgrades.iter().find(|upgrade| upgrade.id == id)
+    }
+
+    /// Draws `count` distinct upgrades as poll options, the same seed yields the same draw.
+    /// The options keep the order of the catalogue.
+    pub fn draw(&self, seed: u64, count: usize) -> Vec<PollOption> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut indices = index::sample(
+            &mut rng,
+            self.upgrades.len(),
+            count.min(self.upgrades.len()),
+        )
+        .into_vec();
+        indices.sort_unstable();
+        indices
+            .into_iter()
+            .map(|i| PollOption {
+                id: self.upgrades[i].id.clone(),
+                label: self.upgrades[i].label.clone(),
+            })
+            .collect()
+    }
+}
@@ -1,5 +1,6 @@
 use num_bigint::BigInt;
 use serde::{Deserialize, Deserializer, Serializer};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::signal;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
@@ -8,6 +9,9 @@ use tracing_subscriber::EnvFilter;
 pub const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 pub const ONE_GOOGOL: &str = "10000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000";
 pub const UPDATE_PERIOD_MS: u64 = 250;
+pub const PRESENCE_PERIOD_MS: u64 = 2000;
+pub const SCHEDULE_PERIOD_MS: u64 = 2000;
+pub const POLL_DURATION_SECS: u64 = 300;
 
 /// Initialize tracing
 pub fn logging() {
@@ -22,6 +26,14 @@ pub async fn shutdown_signal() {
     signal::ctrl_c().await.expect("Failed to listen for Ctrl+C");
 }
 
+/// Current unix time in seconds
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
 /// Convert a BigInt to its decimal string representation
 pub fn serialize_bigint<S>(bigint: &BigInt, serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -39,3 +51,32 @@ where
     BigInt::parse_bytes(s.as_bytes(), 10)
         .ok_or_else(|| serde::de::Error::custom("Failed to parse BigInt from string"))
 }
+
+/// Maps an upgrade stored as poll state before the catalogue to its id,
+/// a poll state without a choice maps to `None`
+fn migrate_upgrade_id(id: String) -> Option<String> {
+    match id.as_str() {
+        "Pending" | "Abstain" => None,
+        "Base" => Some("base".into()),
+        "Exponent" => Some("exponent".into()),
+        _ => Some(id),
+    }
+}
+
+/// Parse an optional upgrade id, migrating old poll states
+pub fn deserialize_upgrade_id<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let id = Option::<String>::deserialize(deserializer)?;
+    Ok(id.and_then(migrate_upgrade_id))
+}
+
+/// Parse a list of upgrade ids, migrating old poll states
+pub fn deserialize_upgrade_path<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let path = Vec::<String>::deserialize(deserializer)?;
+    Ok(path.into_iter().filter_map(migrate_upgrade_id).collect())
+}
@@ -1,38 +1,74 @@
 use crate::{
-    counter::{CountMeter, Counter, PollMeter},
+    account::{Account, AccountStore},
+    bot::{BotConfig, BotStats},
+    challenge::Challenger,
+    contribution::Contributions,
+    counter::{CountMeter, PollMeter, PollOption},
+    engine::{ClientInput, GameEngine, TickInput},
+    event::{Event, Player},
+    name,
+    net::ClientInfo,
+    schedule::ScheduleStore,
+    season::HallOfFame,
+    security::AllowedOrigins,
     util,
 };
 use axum::{
     extract::{
         ws::{Message, WebSocket},
-        State, WebSocketUpgrade,
+        Extension, Query, State, WebSocketUpgrade,
     },
-    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
-use futures::{SinkExt, StreamExt};
+use futures::{stream::SplitSink, SinkExt, StreamExt};
+use num_bigint::BigInt;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
 use tokio::{
-    sync::{broadcast::Sender, RwLock},
+    sync::{broadcast::Sender, Mutex, RwLock},
     time::{self, Duration},
 };
+use tracing::{error, info, warn};
 
 /// Websocket state
 #[derive(Debug)]
 pub struct WebSocketState {
-    pub counter: RwLock<Counter>,
+    pub engine: RwLock<GameEngine>,
+    pub hall_of_fame: RwLock<HallOfFame>,
+    pub contributions: RwLock<Contributions>,
     pub clients: RwLock<HashMap<usize, Client>>,
     pub sender: Sender<String>,
     pub next_client_id: RwLock<usize>,
+    pub bot: BotConfig,
+    /// Timed events modifying the counter
+    pub schedule: Arc<ScheduleStore>,
 }
 
 impl WebSocketState {
-    pub fn new(counter: Counter, sender: Sender<String>) -> Arc<Self> {
+    pub fn new(
+        engine: GameEngine,
+        hall_of_fame: HallOfFame,
+        contributions: Contributions,
+        bot: BotConfig,
+        schedule: Arc<ScheduleStore>,
+        sender: Sender<String>,
+    ) -> Arc<Self> {
         Arc::new(Self {
-            counter: RwLock::new(counter),
+            engine: RwLock::new(engine),
+            hall_of_fame: RwLock::new(hall_of_fame),
+            contributions: RwLock::new(contributions),
             clients: RwLock::new(HashMap::new()),
             sender,
             next_client_id: RwLock::new(1),
+            bot,
+            schedule,
         })
     }
 }
@@ -43,10 +79,25 @@ pub struct Client {
     pub counter_state: CounterState,
     pub action_clicks: usize,
     pub poll_state: PollState,
+    /// Stable identity provided by the client, used to persist contributions
+    pub identity: Option<String>,
+    pub nickname: Option<String>,
+    /// Address of the client, as reported by a trusted proxy if configured
+    pub ip: Option<IpAddr>,
+    /// Total added during this connection
+    pub added: BigInt,
+    /// Total removed during this connection
+    pub sabotaged: BigInt,
+    /// Timing statistics for the bot detection
+    pub bot: BotStats,
+    /// Username of the logged in account, its sockets vote once
+    pub account: Option<String>,
+    /// Side picked on join in team mode, kept for the whole connection
+    pub team: Option<CounterState>,
 }
 
 /// Client counter state
-#[derive(Default, Clone, Debug, PartialEq)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum CounterState {
     #[default]
     Pending = 0,
@@ -73,20 +124,31 @@ impl CounterState {
 #[derive(Default, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum PollState {
     #[default]
-    Pending = 0,
-    Base,
-    Exponent,
+    Pending,
+    /// Vote for the option with the given id
+    Vote(String),
+    /// Counts towards the quorum but for no option
+    Abstain,
 }
 
 impl PollState {
-    /// Counts clients states
-    pub fn meter_poll(poll_states: &[Self]) -> PollMeter {
-        let mut meter = PollMeter::default();
-        for poll_state in poll_states {
+    /// Counts clients states, each with its vote weight.
+    /// Votes for options not in `options` count as pending.
+    pub fn meter_poll(poll_states: &[(Self, u32)], options: &[PollOption]) -> PollMeter {
+        let mut meter = PollMeter {
+            votes: options
+                .iter()
+                .map(|option| (option.id.clone(), 0))
+                .collect(),
+            ..Default::default()
+        };
+        for (poll_state, weight) in poll_states {
             match poll_state {
-                Self::Pending => meter.pending += 1,
-                Self::Base => meter.base += 1,
-                Self::Exponent => meter.exponent += 1,
+                Self::Vote(id) if meter.votes.contains_key(id) => {
+                    *meter.votes.get_mut(id).unwrap() += weight
+                }
+                Self::Pending | Self::Vote(_) => meter.pending += weight,
+                Self::Abstain => meter.abstain += weight,
             }
         }
         meter
@@ -94,58 +156,281 @@ impl PollState {
 }
 
 /// Spawns an updater threads which updates the count and sends that to the clients via a channel
-pub fn spawn_updater(state: Arc<WebSocketState>) {
+pub fn spawn_updater(state: Arc<WebSocketState>, hall_of_fame: PathBuf) {
     tokio::spawn(async move {
-        let mut interval = time::interval(Duration::from_millis(util::UPDATE_PERIOD_MS));
-        let mut before = Counter::default();
+        let tick = state.engine.read().await.config.tick;
+        let mut interval = time::interval(tick);
         loop {
             interval.tick().await;
+            update(&state, &hall_of_fame, util::now_secs()).await;
+        }
+    });
+}
 
-            let (counter_states, poll_states) = {
+/// Runs a single tick of the game engine at `now` and broadcasts the results
+pub async fn update(state: &WebSocketState, hall_of_fame: &Path, now: u64) {
+    let (client_ids, clients) = {
+        let mut clients = state.clients.write().await;
+        let contributions = state.contributions.read().await;
+        let mut clients: Vec<_> = clients.iter_mut().collect();
+        clients.sort_by_key(|(id, _)| **id);
+        // The oldest socket of an account decides its side and vote, others only add clicks
+        let mut accounts: HashMap<String, (usize, CounterState)> = HashMap::new();
+        clients
+            .into_iter()
+            .enumerate()
+            .map(|(index, (id, client))| {
+                let weight = state.bot.weight(&client.bot);
+                let mut input = if weight > 0.0 {
+                    ClientInput {
+                        counter_state: client.counter_state.clone(),
+                        action_clicks: (client.action_clicks as f64 * weight) as usize,
+                        poll_state: client.poll_state.clone(),
+                        // Persisted totals weigh votes across connections
+                        added: client
+                            .identity
+                            .as_ref()
+                            .and_then(|identity| contributions.contributors.get(identity))
+                            .map_or_else(|| client.added.clone(), |c| c.added.clone()),
+                        duplicate_of: None,
+                    }
+                } else {
+                    // Ignored bots count as idle
+                    ClientInput::default()
+                };
+                if let Some(account) = &client.account {
+                    match accounts.entry(account.to_lowercase()) {
+                        Entry::Occupied(first) => {
+                            let (first, counter_state) = first.get();
+                            input.counter_state = counter_state.clone();
+                            input.poll_state = PollState::Pending;
+                            input.duplicate_of = Some(*first);
+                        }
+                        Entry::Vacant(entry) => {
+                            entry.insert((index, input.counter_state.clone()));
+                        }
+                    }
+                }
+                // Reset clicks
+                client.action_clicks = 0;
+                (*id, input)
+            })
+            .collect::<(Vec<_>, Vec<_>)>()
+    };
+
+    let modifier = state.schedule.schedule.read().await.modifier(now);
+    let (output, message) = {
+        let mut engine = state.engine.write().await;
+        let output = engine.tick(&TickInput {
+            now,
+            clients,
+            modifier,
+        });
+        let message = output
+            .changed
+            .then(|| serde_json::to_string(&engine.counter).unwrap());
+        (output, message)
+    };
+
+    {
+        let mut clients = state.clients.write().await;
+        let mut contributions = state.contributions.write().await;
+        for (id, share) in client_ids.iter().zip(&output.shares) {
+            let Some(client) = clients.get_mut(id) else {
+                continue;
+            };
+            client.added += &share.added;
+            client.sabotaged += &share.sabotaged;
+            if let Some(identity) = &client.identity {
+                contributions.add(identity, client.nickname.as_ref(), share);
+            }
+        }
+    }
+
+    for event in &output.events {
+        match event {
+            Event::Completed(completion) => {
+                info!("One Googol reached: {:?}", completion);
+                let mut hall_of_fame_lock = state.hall_of_fame.write().await;
+                hall_of_fame_lock.completions.push(completion.clone());
+                let snapshot = hall_of_fame_lock.clone();
+                drop(hall_of_fame_lock);
+                // Written off the async runtime, without holding any lock
+                let path = hall_of_fame.to_path_buf();
+                let result = tokio::task::spawn_blocking(move || snapshot.save_to_file(&path))
+                    .await
+                    .unwrap();
+                if let Err(e) = result {
+                    error!("Error saving hall of fame: {}", e);
+                }
+            }
+            Event::NewSeason { season } => info!("Started season {}", season),
+            Event::Milestone(reached) => info!("Reached milestone {}", reached.name),
+            _ => {}
+        }
+        let _ = state.sender.send(event.to_message());
+    }
+
+    if let Some(message) = message {
+        let _ = state.sender.send(message);
+    }
+}
+
+/// Spawns a task which periodically broadcasts the connected players, throttled
+/// independently of the counter updates
+pub fn spawn_presence(state: Arc<WebSocketState>) {
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_millis(util::PRESENCE_PERIOD_MS));
+        let mut before = Vec::new();
+        loop {
+            interval.tick().await;
+
+            let players = {
                 let clients = state.clients.read().await;
+                let mut clients: Vec<_> = clients.iter().collect();
+                clients.sort_by_key(|(id, _)| **id);
                 clients
-                    .values()
-                    .map(|client| {
-                        (
-                            (client.counter_state.clone(), client.action_clicks),
-                            client.poll_state.clone(),
-                        )
+                    .into_iter()
+                    .map(|(_, client)| Player {
+                        name: client.nickname.clone(),
+                        counter_state: client.counter_state.clone(),
+                        poll_state: client.poll_state.clone(),
                     })
-                    .collect::<(Vec<(_, _)>, Vec<_>)>()
+                    .collect::<Vec<_>>()
             };
 
-            // Reset clicks
-            for client in state.clients.write().await.values_mut() {
-                client.action_clicks = 0;
+            if before != players {
+                let _ = state.sender.send(
+                    Event::Presence {
+                        players: players.clone(),
+                    }
+                    .to_message(),
+                );
+                before = players;
             }
+        }
+    });
+}
 
-            let mut counter = state.counter.write().await;
-
-            counter.update_poll(&poll_states);
-            counter.update_count(&counter_states);
+/// Spawns a task which periodically broadcasts the running events with their remaining
+/// time, once more after the last one ended
+pub fn spawn_schedule(state: Arc<WebSocketState>) {
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_millis(util::SCHEDULE_PERIOD_MS));
+        let mut running = false;
+        loop {
+            interval.tick().await;
 
-            if before != *counter {
-                let message = serde_json::to_string(&*counter).unwrap();
-                let _ = state.sender.send(message);
-                before = counter.clone();
+            let active = state
+                .schedule
+                .schedule
+                .read()
+                .await
+                .active(util::now_secs());
+            if running || !active.is_empty() {
+                running = !active.is_empty();
+                let _ = state.sender.send(Event::Schedule { active }.to_message());
             }
-
-            drop(counter);
         }
     });
 }
 
+/// Query parameters of the `/ws` route
+#[derive(Debug, Default, Deserialize)]
+pub struct WsParams {
+    /// Stable identity generated and stored by the client
+    pub identity: Option<String>,
+    pub name: Option<String>,
+    /// Token from `GET /api/challenge`, required if joins are gated
+    pub challenge: Option<String>,
+    /// Proof-of-work solving the challenge
+    pub solution: Option<String>,
+    /// Side to join in team mode, `increment` or `decrement`
+    pub team: Option<String>,
+}
+
+impl WsParams {
+    /// Returns the identity if it consists of 16 to 64 alphanumeric characters or dashes
+    fn valid_identity(&self) -> Option<String> {
+        self.identity
+            .as_ref()
+            .filter(|id| {
+                (16..=64).contains(&id.len())
+                    && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            })
+            .cloned()
+    }
+
+    /// Returns the requested team if it names a side
+    fn valid_team(&self) -> Option<CounterState> {
+        match self.team.as_deref() {
+            Some("increment") => Some(CounterState::Increment),
+            Some("decrement") => Some(CounterState::Decrement),
+            _ => None,
+        }
+    }
+
+    /// Returns the name if it passes the nickname validation
+    fn valid_name(&self) -> Option<String> {
+        self.name
+            .as_ref()
+            .and_then(|name| name::validate(name).ok())
+    }
+}
+
+/// Checks and lookups before a websocket joins, inserted as extension
+#[derive(Debug, Clone)]
+pub struct JoinConfig {
+    pub origins: AllowedOrigins,
+    /// Proof-of-work required to join, if gated
+    pub challenger: Option<Arc<Challenger>>,
+    pub accounts: Arc<AccountStore>,
+}
+
 /// WebSocket handler for the `/ws` route
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
+    Query(params): Query<WsParams>,
+    Extension(info): Extension<ClientInfo>,
+    Extension(join): Extension<JoinConfig>,
     State(state): State<Arc<WebSocketState>>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    headers: HeaderMap,
+) -> Response {
+    // Other sites must not drive the counter with their visitors
+    if !join.origins.allows(&headers) {
+        warn!(
+            "Rejected websocket from foreign origin {:?}",
+            headers.get("origin")
+        );
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    // Joins cost work before the client is counted
+    if let Some(challenger) = &join.challenger {
+        let result = challenger
+            .verify(
+                params.challenge.as_deref(),
+                params.solution.as_deref(),
+                util::now_secs(),
+            )
+            .await;
+        if let Err(e) = result {
+            warn!("Rejected websocket from {:?}: {}", info.ip, e);
+            return StatusCode::FORBIDDEN.into_response();
+        }
+    }
+    let account = join.accounts.session_user(&headers).await;
+    ws.on_upgrade(move |socket| handle_socket(socket, params, info, account, state))
 }
 
 /// Handles an individual WebSocket connection
-async fn handle_socket(stream: WebSocket, state: Arc<WebSocketState>) {
-    let (mut sender, mut receiver) = stream.split();
+async fn handle_socket(
+    stream: WebSocket,
+    params: WsParams,
+    info: ClientInfo,
+    account: Option<String>,
+    state: Arc<WebSocketState>,
+) {
+    let (sender, mut receiver) = stream.split();
 
     let mut rx = state.sender.subscribe();
 
@@ -156,54 +441,147 @@ async fn handle_socket(stream: WebSocket, state: Arc<WebSocketState>) {
         id
     };
 
+    let teams = state.engine.read().await.config.teams;
     let mut clients = state.clients.write().await;
-    clients.insert(client_id, Client::default());
+    // Without a pick the client joins the smaller team
+    let team = teams.then(|| {
+        params.valid_team().unwrap_or_else(|| {
+            let increment = clients
+                .values()
+                .filter(|client| client.team == Some(CounterState::Increment))
+                .count();
+            if increment * 2 > clients.len() {
+                CounterState::Decrement
+            } else {
+                CounterState::Increment
+            }
+        })
+    });
+    clients.insert(
+        client_id,
+        Client {
+            counter_state: team.clone().unwrap_or_default(),
+            team: team.clone(),
+            // Accounts keep their contributions across devices
+            identity: account
+                .as_deref()
+                .map(Account::identity)
+                .or_else(|| params.valid_identity()),
+            nickname: params.valid_name().or_else(|| account.clone()),
+            ip: info.ip,
+            account,
+            ..Default::default()
+        },
+    );
     drop(clients);
 
-    let send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            if sender.send(Message::Text(msg.into())).await.is_err() {
-                break;
+    // Shared with the command loop, which replies to its client directly
+    let sender = Arc::new(Mutex::new(sender));
+    if let Some(team) = team.clone() {
+        reply(&sender, Event::Joined { team }).await;
+    }
+    let send_task = tokio::spawn({
+        let sender = sender.clone();
+        async move {
+            while let Ok(msg) = rx.recv().await {
+                if sender
+                    .lock()
+                    .await
+                    .send(Message::Text(msg.into()))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
             }
         }
     });
 
     while let Some(Ok(message)) = receiver.next().await {
         if let Message::Text(text) = message {
-            match text.as_str() {
-                "increment" => {
+            record_timing(&state, client_id, &text).await;
+            let rejected = match text.as_str() {
+                "increment" | "decrement" => {
+                    let counter_state = if text.as_str() == "increment" {
+                        CounterState::Increment
+                    } else {
+                        CounterState::Decrement
+                    };
                     let mut clients = state.clients.write().await;
-                    if let Some(client) = clients.get_mut(&client_id) {
-                        client.counter_state = CounterState::Increment;
+                    match clients.get_mut(&client_id) {
+                        Some(client)
+                            if client
+                                .team
+                                .as_ref()
+                                .is_some_and(|team| *team != counter_state) =>
+                        {
+                            warn!("Client {} cannot switch teams", client_id);
+                            Some("teams cannot be switched while connected".to_string())
+                        }
+                        Some(client) => {
+                            client.counter_state = counter_state;
+                            None
+                        }
+                        None => None,
                     }
                 }
-                "decrement" => {
+                "abstain" => {
                     let mut clients = state.clients.write().await;
                     if let Some(client) = clients.get_mut(&client_id) {
-                        client.counter_state = CounterState::Decrement;
+                        client.poll_state = PollState::Abstain;
                     }
+                    None
                 }
-                "base" => {
+                "action" => {
                     let mut clients = state.clients.write().await;
                     if let Some(client) = clients.get_mut(&client_id) {
-                        client.poll_state = PollState::Base;
+                        client.action_clicks += 1;
                     }
+                    None
                 }
-                "exponent" => {
-                    let mut clients = state.clients.write().await;
-                    if let Some(client) = clients.get_mut(&client_id) {
-                        client.poll_state = PollState::Exponent;
+                command if command.starts_with("vote ") => {
+                    let id = &command["vote ".len()..];
+                    // Only offered ids, votes are shown to all players
+                    let offered = state
+                        .engine
+                        .read()
+                        .await
+                        .counter
+                        .running_poll(team.as_ref())
+                        .is_some_and(|poll| poll.options.iter().any(|option| option.id == id));
+                    if !offered {
+                        warn!("Rejected vote of client {}", client_id);
+                        Some("invalid upgrade id".to_string())
+                    } else {
+                        let mut clients = state.clients.write().await;
+                        if let Some(client) = clients.get_mut(&client_id) {
+                            client.poll_state = PollState::Vote(id.to_string());
+                        }
+                        None
                     }
                 }
-                "action" => {
-                    let mut clients = state.clients.write().await;
-                    if let Some(client) = clients.get_mut(&client_id) {
-                        client.action_clicks += 1;
+                command if command.starts_with("set_name ") => {
+                    match name::validate(&command["set_name ".len()..]) {
+                        Ok(nickname) => {
+                            let mut clients = state.clients.write().await;
+                            if let Some(client) = clients.get_mut(&client_id) {
+                                client.nickname = Some(nickname);
+                            }
+                            None
+                        }
+                        Err(e) => {
+                            warn!("Rejected name of client {}: {}", client_id, e);
+                            Some(e.to_string())
+                        }
                     }
                 }
                 _ => {
                     println!("Unknown command from client {}: {}", client_id, text);
+                    None
                 }
+            };
+            if let Some(reason) = rejected {
+                reply(&sender, Event::Rejected { reason }).await;
             }
         } else if let Message::Close(_) = message {
             break;
@@ -216,3 +594,25 @@ async fn handle_socket(stream: WebSocket, state: Arc<WebSocketState>) {
 
     send_task.abort();
 }
+
+/// Sends an event to a single client
+async fn reply(sender: &Mutex<SplitSink<WebSocket, Message>>, event: Event) {
+    // A closed socket ends the command loop as well
+    let _ = sender
+        .lock()
+        .await
+        .send(Message::Text(event.to_message().into()))
+        .await;
+}
+
+/// Feeds a command into the bot statistics of a client
+async fn record_timing(state: &WebSocketState, client_id: usize, command: &str) {
+    let now = Instant::now();
+    let mut clients = state.clients.write().await;
+    if let Some(client) = clients.get_mut(&client_id) {
+        match command {
+            "action" => client.bot.action(now),
+            command => client.bot.command(command, now),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::TestServer;
+use num_bigint::BigInt;
+use one_googol::ws::CounterState;
+use serde_json::json;
+
+#[tokio::test]
+async fn register_and_login() {
+    let server = TestServer::start().await;
+    let credentials = json!({ "username": "Alice", "password": "correct horse" });
+
+    let (status, cookie, _) = server.post("/api/register", credentials.clone()).await;
+    assert_eq!(status, 201);
+    assert!(cookie.unwrap().starts_with("session="));
+
+    let (status, _, _) = server.post("/api/register", credentials).await;
+    assert_eq!(status, 409);
+
+    let wrong = json!({ "username": "alice", "password": "battery staple" });
+    assert_eq!(server.post("/api/login", wrong).await.0, 401);
+
+    let (status, cookie, body) = server
+        .post(
+            "/api/login",
+            json!({ "username": "alice", "password": "correct horse" }),
+        )
+        .await;
+    assert_eq!(status, 200);
+    assert!(cookie.is_some());
+    assert_eq!(body, r#"{"username":"Alice"}"#);
+
+    let short = json!({ "username": "bob", "password": "short" });
+    assert_eq!(server.post("/api/register", short).await.0, 400);
+}
+
+#[tokio::test]
+async fn sockets_of_an_account_count_once() {
+    let server = TestServer::start().await;
+    let credentials = json!({ "username": "alice", "password": "correct horse" });
+    let (_, cookie, _) = server.post("/api/register", credentials).await;
+    let cookie = cookie.unwrap();
+
+    let mut first = server.connect_with_cookie("", Some(&cookie)).await;
+    let mut second = server.connect_with_cookie("", Some(&cookie)).await;
+    first.send("increment").await;
+    first.send("action").await;
+    // The second socket plays the side of the first one, its clicks still count
+    second.send("decrement").await;
+    second.send("action").await;
+    second.send("action").await;
+    server
+        .wait_for(|c| {
+            c.values().map(|c| c.action_clicks).sum::<usize>() == 3
+                && c.values().all(|c| c.counter_state != CounterState::Pending)
+        })
+        .await;
+    assert!(server
+        .state
+        .clients
+        .read()
+        .await
+        .values()
+        .all(|c| c.identity.as_deref() == Some("account:alice")));
+    server.tick(0).await;
+
+    let counter = first.next_counter().await;
+    assert_eq!(counter.count.meter.increment, 1);
+    assert_eq!(counter.count.meter.decrement, 0);
+    assert_eq!(counter.count.meter.pending, 0);
+    // One step and three clicks
+    assert_eq!(counter.count.value, BigInt::from(4));
+    let contributions = server.state.contributions.read().await;
+    assert_eq!(
+        contributions.contributors["account:alice"].added,
+        BigInt::from(4)
+    );
+}
+
+#[tokio::test]
+async fn failed_logins_are_throttled() {
+    let server = TestServer::start().await;
+    let credentials = json!({ "username": "carol", "password": "correct horse" });
+    server.post("/api/register", credentials.clone()).await;
+
+    let unknown = json!({ "username": "dave", "password": "battery staple" });
+    assert_eq!(server.post("/api/login", unknown).await.0, 401);
+    let long = json!({ "username": "carol", "password": "a".repeat(10_000) });
+    assert_eq!(server.post("/api/login", long).await.0, 401);
+    for _ in 0..3 {
+        let wrong = json!({ "username": "carol", "password": "battery staple" });
+        assert_eq!(server.post("/api/login", wrong).await.0, 401);
+    }
+    // Five failures from this address, even the right password is refused now
+    assert_eq!(server.post("/api/login", credentials).await.0, 429);
+}
+
+#[tokio::test]
+async fn registrations_are_throttled() {
+    let server = TestServer::start().await;
+    for i in 0..5 {
+        let credentials = json!({ "username": format!("user{i}"), "password": "correct horse" });
+        assert_eq!(server.post("/api/register", credentials).await.0, 201);
+    }
+    // Five registrations from this address, further ones are refused before hashing
+    let credentials = json!({ "username": "user5", "password": "correct horse" });
+    assert_eq!(server.post("/api/register", credentials).await.0, 429);
+}
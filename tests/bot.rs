@@ -0,0 +1,65 @@
+use one_googol::bot::{BotConfig, BotPolicy, BotStats};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+#[test]
+fn perfectly_regular_clicks_are_flagged() {
+    let start = Instant::now();
+    let mut rng = StdRng::seed_from_u64(1);
+
+    let mut script = BotStats::default();
+    let mut human = BotStats::default();
+    let mut human_at = start;
+    for i in 0..100 {
+        script.action(start + Duration::from_millis(100 * i));
+        human_at += Duration::from_millis(rng.gen_range(80..400));
+        human.action(human_at);
+    }
+
+    assert!(script.score() >= 0.8);
+    assert!(human.score() < 0.5);
+
+    let config = BotConfig {
+        policy: BotPolicy::Ignore,
+        threshold: 0.8,
+    };
+    assert_eq!(config.weight(&script), 0.0);
+    assert_eq!(config.weight(&human), 1.0);
+}
+
+#[test]
+fn fast_scripted_toggling_is_flagged() {
+    let start = Instant::now();
+
+    let mut toggler = BotStats::default();
+    let mut switcher = BotStats::default();
+    for i in 0..40 {
+        let command = if i % 2 == 0 { "increment" } else { "decrement" };
+        toggler.command(command, start + Duration::from_millis(50 * i));
+        switcher.command(command, start + Duration::from_secs(20 * i));
+    }
+
+    assert_eq!(toggler.command_entropy(), Some(0.0));
+    assert!(toggler.score() >= 0.8);
+    // Switching sides every now and then is just as predictable, but human paced
+    assert_eq!(switcher.score(), 0.0);
+}
+
+#[test]
+fn scripted_vote_switching_is_flagged() {
+    let start = Instant::now();
+
+    let mut voter = BotStats::default();
+    let mut human = BotStats::default();
+    let mut rng = StdRng::seed_from_u64(2);
+    let commands = ["vote base", "vote exponent", "abstain", "vote frenzy"];
+    for i in 0..40 {
+        let at = start + Duration::from_millis(50 * i);
+        voter.command(commands[i as usize % 2], at);
+        human.command(commands[rng.gen_range(0..commands.len())], at);
+    }
+
+    assert_eq!(voter.command_entropy(), Some(0.0));
+    assert!(voter.score() >= 0.8);
+    assert!(human.command_entropy().unwrap() > 1.0);
+}
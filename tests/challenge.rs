@@ -0,0 +1,64 @@
+use one_googol::challenge::{leading_zeros, ChallengeConfig, ChallengeError, Challenger};
+
+fn config() -> ChallengeConfig {
+    ChallengeConfig {
+        difficulty: 8,
+        load_threshold: 100,
+        max_difficulty: 10,
+        ttl: 60,
+    }
+}
+
+fn solve(challenge: &str, difficulty: u32) -> String {
+    (0u64..)
+        .map(|n| n.to_string())
+        .find(|solution| leading_zeros(challenge, solution) >= difficulty)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn solved_challenges_are_accepted_once() {
+    let challenger = Challenger::new(config());
+    let issued = challenger.issue(0, 1000);
+    let solution = solve(&issued.challenge, issued.difficulty);
+
+    assert_eq!(
+        challenger
+            .verify(Some(&issued.challenge), Some(&solution), 1030)
+            .await,
+        Ok(())
+    );
+    assert_eq!(
+        challenger
+            .verify(Some(&issued.challenge), Some(&solution), 1030)
+            .await,
+        Err(ChallengeError::Reused)
+    );
+
+    let issued = challenger.issue(0, 1000);
+    let solution = solve(&issued.challenge, issued.difficulty);
+    assert_eq!(
+        challenger
+            .verify(Some(&issued.challenge), Some(&solution), 1061)
+            .await,
+        Err(ChallengeError::Expired)
+    );
+    let forged = issued.challenge.replace(".8.", ".0.");
+    assert_eq!(
+        challenger.verify(Some(&forged), Some("0"), 1000).await,
+        Err(ChallengeError::Malformed)
+    );
+    assert_eq!(
+        challenger.verify(None, None, 1000).await,
+        Err(ChallengeError::Missing)
+    );
+}
+
+#[test]
+fn difficulty_adapts_to_load() {
+    let config = config();
+    assert_eq!(config.difficulty(99), 8);
+    assert_eq!(config.difficulty(100), 9);
+    assert_eq!(config.difficulty(200), 10);
+    assert_eq!(config.difficulty(100_000), 10);
+}
@@ -0,0 +1,253 @@
+//! Test harness serving the app over plain http on an ephemeral port.
+
+#![allow(dead_code)]
+
+use axum::Router;
+use futures::{SinkExt, StreamExt};
+use one_googol::{
+    account::{AccountStore, Accounts},
+    app::{build_app, AppConfig},
+    bot::BotConfig,
+    contribution::Contributions,
+    counter::Counter,
+    engine::{EngineConfig, GameEngine},
+    net::ProxyConfig,
+    schedule::{Schedule, ScheduleStore},
+    season::HallOfFame,
+    security::SecurityConfig,
+    ws::{self, Client, WebSocketState},
+};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, TcpListener},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+use tempfile::TempDir;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::broadcast,
+    time,
+};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+/// The router and its state, with all files in a temporary directory
+pub struct TestApp {
+    pub router: Router,
+    pub state: Arc<WebSocketState>,
+    pub dir: TempDir,
+}
+
+impl TestApp {
+    /// Builds the app around the given engine, the view is served from `<dir>/view`
+    pub fn new(engine: GameEngine) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let view = dir.path().join("view");
+        std::fs::create_dir(&view).unwrap();
+        std::fs::write(view.join("index.html"), "<h1>One Googol</h1>").unwrap();
+
+        let (sender, _) = broadcast::channel(100);
+        let state = WebSocketState::new(
+            engine,
+            HallOfFame::default(),
+            Contributions::default(),
+            BotConfig::default(),
+            Arc::new(ScheduleStore::new(
+                Schedule::default(),
+                dir.path().join("schedule.json"),
+            )),
+            sender,
+        );
+        let router = build_app(
+            &AppConfig {
+                view,
+                proxy: ProxyConfig {
+                    tls: false,
+                    trust_proxy: false,
+                },
+                security: SecurityConfig::default(),
+                admin_token: None,
+                challenge: None,
+                accounts: Arc::new(AccountStore::new(
+                    Accounts::default(),
+                    dir.path().join("accounts.json"),
+                )),
+            },
+            state.clone(),
+        );
+        Self { router, state, dir }
+    }
+
+    /// Path of the view directory
+    pub fn view(&self) -> PathBuf {
+        self.dir.path().join("view")
+    }
+}
+
+/// A running server with manually advanced ticks
+pub struct TestServer {
+    pub addr: SocketAddr,
+    pub state: Arc<WebSocketState>,
+    pub dir: TempDir,
+    handle: axum_server::Handle,
+}
+
+impl TestServer {
+    /// Starts a server with a fresh counter
+    pub async fn start() -> Self {
+        Self::with_engine(GameEngine::new(Counter::new(0), EngineConfig::default())).await
+    }
+
+    /// Starts a server with the given engine
+    pub async fn with_engine(engine: GameEngine) -> Self {
+        let TestApp { router, state, dir } = TestApp::new(engine);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = axum_server::Handle::new();
+        let server = axum_server::from_tcp(listener)
+            .handle(handle.clone())
+            .serve(router.into_make_service_with_connect_info::<SocketAddr>());
+        tokio::spawn(server);
+
+        Self {
+            addr,
+            state,
+            dir,
+            handle,
+        }
+    }
+
+    /// Path of the hall of fame file
+    pub fn hall_of_fame(&self) -> PathBuf {
+        self.dir.path().join("hall_of_fame.json")
+    }
+
+    /// Runs one tick at the unix time `now`
+    pub async fn tick(&self, now: u64) {
+        ws::update(&self.state, &self.hall_of_fame(), now).await;
+    }
+
+    /// Connects a websocket client, `query` is appended to `/ws`
+    pub async fn connect(&self, query: &str) -> TestClient {
+        self.connect_with_cookie(query, None).await
+    }
+
+    /// Connects a websocket client sending a `Cookie` header
+    pub async fn connect_with_cookie(&self, query: &str, cookie: Option<&str>) -> TestClient {
+        let clients = self.state.clients.read().await.len();
+        let mut request = format!("ws://{}/ws{query}", self.addr)
+            .into_client_request()
+            .unwrap();
+        if let Some(cookie) = cookie {
+            request
+                .headers_mut()
+                .insert("cookie", cookie.parse().unwrap());
+        }
+        let (stream, _) = connect_async(request).await.unwrap();
+        self.wait_for(|c| c.len() > clients).await;
+        TestClient { stream }
+    }
+
+    /// Waits until the connected clients satisfy `condition`
+    pub async fn wait_for(&self, condition: impl Fn(&HashMap<usize, Client>) -> bool) {
+        time::timeout(Duration::from_secs(5), async {
+            while !condition(&*self.state.clients.read().await) {
+                time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("condition on clients not reached");
+    }
+
+    /// Sends a plain http `GET` request and returns the status and body
+    pub async fn get(&self, path: &str) -> (u16, String) {
+        let (status, _, body) = self.request("GET", path, "").await;
+        (status, body)
+    }
+
+    /// Sends a plain http `POST` request with a json body and returns the status,
+    /// the `Set-Cookie` value and the body
+    pub async fn post(&self, path: &str, json: Value) -> (u16, Option<String>, String) {
+        self.request("POST", path, &json.to_string()).await
+    }
+
+    async fn request(&self, method: &str, path: &str, body: &str) -> (u16, Option<String>, String) {
+        let mut stream = TcpStream::connect(self.addr).await.unwrap();
+        let request = format!(
+            "{method} {path} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            self.addr,
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        let cookie = head
+            .lines()
+            .find_map(|line| line.strip_prefix("set-cookie: "))
+            .map(|cookie| cookie.split(';').next().unwrap().to_string());
+        // Only length delimited bodies are expected here
+        (status, cookie, body.to_string())
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.handle.shutdown();
+    }
+}
+
+/// A websocket client of the test server
+pub struct TestClient {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl TestClient {
+    /// Sends a text command
+    pub async fn send(&mut self, command: &str) {
+        self.stream.send(Message::text(command)).await.unwrap();
+    }
+
+    /// Receives the next message as json
+    pub async fn next(&mut self) -> Value {
+        let message = time::timeout(Duration::from_secs(5), self.stream.next())
+            .await
+            .expect("no message received")
+            .unwrap()
+            .unwrap();
+        serde_json::from_str(message.to_text().unwrap()).unwrap()
+    }
+
+    /// Receives the next counter update, skipping events
+    pub async fn next_counter(&mut self) -> Counter {
+        loop {
+            let message = self.next().await;
+            if message.get("event").is_none() {
+                return serde_json::from_value(message).unwrap();
+            }
+        }
+    }
+
+    /// Receives the next event with the given name, skipping everything else
+    pub async fn next_event(&mut self, name: &str) -> Value {
+        loop {
+            let message = self.next().await;
+            if message.get("event").and_then(Value::as_str) == Some(name) {
+                return message;
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use num_bigint::BigInt;
+use one_googol::{
+    contribution::{attribute, Contributions, Share, MAX_ANONYMOUS_CONTRIBUTORS},
+    counter::Step,
+    ws::CounterState,
+};
+
+#[test]
+fn steps_are_split_by_side_and_clicks() {
+    let step = Step {
+        increment: BigInt::from(10),
+        increment_modifier: BigInt::from(9),
+        decrement: BigInt::from(7),
+        decrement_modifier: BigInt::from(0),
+    };
+    let shares = attribute(
+        &step,
+        &[
+            (CounterState::Increment, 2),
+            (CounterState::Increment, 1),
+            (CounterState::Decrement, 0),
+            (CounterState::Pending, 5),
+        ],
+    );
+    let share = |added: i32, sabotaged: i32| Share {
+        added: BigInt::from(added),
+        sabotaged: BigInt::from(sabotaged),
+    };
+    assert_eq!(
+        shares,
+        [share(11, 0), share(8, 0), share(0, 7), share(0, 0)]
+    );
+
+    let mut contributions = Contributions::default();
+    contributions.add("a", None, &shares[1]);
+    contributions.add("b", Some(&"Bob".into()), &shares[0]);
+    contributions.add("b", None, &shares[2]);
+    let leaderboard = contributions.leaderboard(1);
+    assert_eq!(leaderboard.len(), 1);
+    assert_eq!(leaderboard[0].nickname.as_deref(), Some("Bob"));
+    assert_eq!(leaderboard[0].added, BigInt::from(11));
+    assert_eq!(leaderboard[0].sabotaged, BigInt::from(7));
+}
+
+#[test]
+fn anonymous_contributors_are_bounded() {
+    let mut contributions = Contributions::default();
+    let added = |added: usize| Share {
+        added: BigInt::from(added),
+        sabotaged: BigInt::from(0),
+    };
+    contributions.add("idle-client", None, &Share::default());
+    assert!(contributions.contributors.is_empty());
+
+    contributions.add("account:alice", None, &added(1));
+    for i in 0..MAX_ANONYMOUS_CONTRIBUTORS {
+        contributions.add(&format!("client-{i}"), None, &added(i + 2));
+    }
+    contributions.add("newcomer", None, &added(1));
+    // The anonymous identity which added the least made room, accounts stay
+    assert_eq!(
+        contributions.contributors.len(),
+        MAX_ANONYMOUS_CONTRIBUTORS + 1
+    );
+    assert!(!contributions.contributors.contains_key("client-0"));
+    assert!(contributions.contributors.contains_key("newcomer"));
+    assert!(contributions.contributors.contains_key("account:alice"));
+}
@@ -0,0 +1,110 @@
+//! Serving of the embedded frontend, run with
+//! `EMBED_VIEW=tests/view cargo test --features embed --test embed`
+
+#![cfg(feature = "embed")]
+
+mod common;
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header, Request, Response, StatusCode},
+    Router,
+};
+use common::TestApp;
+use http_body_util::BodyExt;
+use one_googol::{
+    assets,
+    counter::Counter,
+    engine::{EngineConfig, GameEngine},
+};
+use tower::ServiceExt;
+
+fn app() -> Router {
+    TestApp::new(GameEngine::new(Counter::new(0), EngineConfig::default())).router
+}
+
+async fn get(
+    app: Router,
+    path: &str,
+    headers: &[(header::HeaderName, &str)],
+) -> (Response<Body>, Bytes) {
+    let mut request = Request::get(path);
+    for (name, value) in headers {
+        request = request.header(name, *value);
+    }
+    let (parts, body) = app
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .into_parts();
+    let body = body.collect().await.unwrap().to_bytes();
+    (Response::from_parts(parts, Body::empty()), body)
+}
+
+#[tokio::test]
+async fn embedded_files_are_cached_by_kind() {
+    let (response, body) = get(app(), "/faq", &[]).await;
+    assert_eq!(body, "faq");
+    assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html");
+
+    let (response, _) = get(app(), "/_app/immutable/app.js", &[]).await;
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "public, max-age=31536000, immutable"
+    );
+
+    let (response, _) = get(app(), "/robots.txt", &[]).await;
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "public, max-age=86400"
+    );
+
+    // Revalidated with the content hash
+    let etag = assets::get("faq.html").unwrap().etag;
+    let (response, body) = get(app(), "/faq", &[(header::IF_NONE_MATCH, etag)]).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[header::ETAG], etag);
+    assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+    assert!(body.is_empty());
+}
+
+#[tokio::test]
+async fn embedded_files_are_served_compressed() {
+    let asset = assets::get("_app/immutable/app.js").unwrap();
+    let path = "/_app/immutable/app.js";
+
+    // Generated from the file, the precompressed sibling of the build is skipped
+    assert!(assets::get("_app/immutable/app.js.br").is_none());
+    let (response, body) = get(app(), path, &[(header::ACCEPT_ENCODING, "gzip, br")]).await;
+    assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+    assert_eq!(response.headers()[header::VARY], "accept-encoding");
+    assert_eq!(body, asset.brotli.unwrap());
+
+    let (response, body) = get(app(), path, &[(header::ACCEPT_ENCODING, "gzip, br;q=0")]).await;
+    assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+    assert_eq!(body, asset.gzip.unwrap());
+
+    let (response, body) = get(app(), path, &[(header::ACCEPT_ENCODING, "identity")]).await;
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(body, asset.raw);
+
+    // Compression is only kept if it saves enough
+    let robots = assets::get("robots.txt").unwrap();
+    assert!(robots.brotli.is_none() && robots.gzip.is_none());
+    let (response, _) = get(app(), "/robots.txt", &[(header::ACCEPT_ENCODING, "br")]).await;
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+}
+
+#[tokio::test]
+async fn unknown_embedded_paths_serve_the_404_page() {
+    assert!(assets::get("missing.html").is_none());
+    assert!(assets::get("404.html").is_some());
+
+    for path in ["/missing", "/missing.js", "/../index.html"] {
+        let (response, body) = get(app(), path, &[(header::IF_NONE_MATCH, "*")]).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{path}");
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+        assert_eq!(body, "not found");
+    }
+}
@@ -0,0 +1,169 @@
+use num_bigint::BigInt;
+use one_googol::{
+    counter::Counter,
+    engine::{ClientInput, EngineConfig, GameEngine, TickInput},
+    event::Event,
+    ws::{CounterState, PollState},
+};
+use std::time::Duration;
+
+fn one_googol() -> BigInt {
+    BigInt::from(10).pow(100)
+}
+
+fn client(counter_state: CounterState, poll_state: PollState) -> ClientInput {
+    ClientInput {
+        counter_state,
+        poll_state,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn completion_freezes_the_counter_until_the_next_season() {
+    let mut counter = Counter::new(0);
+    counter.count.value = one_googol() - 1;
+    let config = EngineConfig {
+        new_season_after: Some(Duration::from_secs(60)),
+        ..Default::default()
+    };
+    let mut engine = GameEngine::new(counter, config);
+    let input = |now| TickInput {
+        now,
+        clients: vec![client(CounterState::Increment, PollState::Pending)],
+        ..Default::default()
+    };
+
+    let output = engine.tick(&input(100));
+    assert!(matches!(output.events.last(), Some(Event::Completed(_))));
+    assert_eq!(engine.counter.count.value, one_googol());
+    assert_eq!(engine.counter.season.completed_at, Some(100));
+
+    // Frozen until the delay passed
+    let output = engine.tick(&input(159));
+    assert!(output.events.is_empty());
+    assert!(!output.changed);
+    assert_eq!(engine.counter.count.value, one_googol());
+
+    let output = engine.tick(&input(160));
+    assert_eq!(output.events, [Event::NewSeason { season: 2 }]);
+    assert_eq!(engine.counter.count.value, BigInt::from(0));
+    assert_eq!(engine.counter.season.started_at, 160);
+}
+
+#[test]
+fn finished_polls_apply_the_winning_upgrade() {
+    let mut counter = Counter::new(0);
+    counter.count.value = BigInt::from(10).pow(9);
+    let config = EngineConfig {
+        tick: Duration::from_secs(100),
+        ..Default::default()
+    };
+    let mut engine = GameEngine::new(counter, config);
+    let input = TickInput {
+        clients: vec![
+            client(CounterState::Pending, PollState::Vote("exponent".into())),
+            client(CounterState::Pending, PollState::Vote("exponent".into())),
+            client(CounterState::Pending, PollState::Vote("base".into())),
+        ],
+        ..Default::default()
+    };
+
+    engine.tick(&input);
+    assert!(engine.counter.poll.is_some());
+    for _ in 0..10 {
+        engine.tick(&input);
+    }
+
+    assert!(engine.counter.poll.is_none());
+    assert_eq!(engine.counter.upgrade.exponent, 1);
+    assert_eq!(engine.counter.upgrade.base, 1);
+    assert_eq!(engine.counter.season.upgrade_path, ["exponent"]);
+}
+
+#[test]
+fn poll_history_is_archived_with_the_season() {
+    let mut counter = Counter::new(0);
+    counter.count.value = BigInt::from(10).pow(9);
+    let config = EngineConfig {
+        tick: Duration::from_secs(100),
+        new_season_after: Some(Duration::from_secs(60)),
+        ..Default::default()
+    };
+    let mut engine = GameEngine::new(counter, config);
+    let voter = client(CounterState::Increment, PollState::Vote("base".into()));
+    for _ in 0..10 {
+        engine.tick(&TickInput {
+            clients: vec![voter.clone()],
+            ..Default::default()
+        });
+    }
+    assert_eq!(engine.counter.poll_history.len(), 1);
+
+    engine.counter.count.value = one_googol() - 1;
+    let output = engine.tick(&TickInput {
+        now: 100,
+        clients: vec![voter.clone()],
+        ..Default::default()
+    });
+    let Some(Event::Completed(completion)) = output.events.last() else {
+        panic!("season not completed");
+    };
+    assert_eq!(completion.poll_history.len(), 1);
+    assert_eq!(completion.poll_history[0].winner, "base");
+
+    // The next season starts with an empty history
+    let output = engine.tick(&TickInput {
+        now: 160,
+        ..Default::default()
+    });
+    assert_eq!(output.events, [Event::NewSeason { season: 2 }]);
+    assert!(engine.counter.poll_history.is_empty());
+}
+
+#[test]
+fn duplicates_fold_into_their_first_client() {
+    let mut engine = GameEngine::new(Counter::new(0), EngineConfig::default());
+    let clicking = |duplicate_of| ClientInput {
+        counter_state: CounterState::Increment,
+        action_clicks: 1,
+        duplicate_of,
+        ..Default::default()
+    };
+    // A valid link, one to itself, a forward and an unknown one
+    let input = TickInput {
+        clients: vec![
+            clicking(None),
+            clicking(Some(0)),
+            clicking(Some(2)),
+            clicking(Some(4)),
+            clicking(Some(100)),
+        ],
+        ..Default::default()
+    };
+
+    let output = engine.tick(&input);
+    assert_eq!(engine.counter.count.meter.increment, 4);
+    assert_eq!(engine.counter.count.accumulated_actions, 5);
+    assert_eq!(output.shares.len(), 5);
+    assert_eq!(output.shares[1].added, BigInt::from(0));
+}
+
+#[test]
+fn engines_are_deterministic() {
+    let run = || {
+        let mut engine = GameEngine::new(Counter::new(7), EngineConfig::default());
+        let input = TickInput {
+            now: 7,
+            clients: vec![client(CounterState::Increment, PollState::Pending)],
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            engine.tick(&input);
+        }
+        engine.counter
+    };
+    let counter = run();
+    assert_eq!(counter.season.started_at, 7);
+    assert_eq!(counter, run());
+}
@@ -0,0 +1,53 @@
+use num_bigint::BigInt;
+use one_googol::{
+    counter::Counter,
+    engine::{ClientInput, EngineConfig, GameEngine, TickInput},
+    event::Event,
+    milestone::{Milestones, Progress},
+};
+
+#[test]
+fn milestones_are_reached_once() {
+    let milestones = Milestones::default();
+    let mut progress = Progress::default();
+    let mut check = |digits, players| -> Vec<String> {
+        milestones
+            .check(&mut progress, digits, players, 0, 0)
+            .into_iter()
+            .map(|reached| reached.id)
+            .collect()
+    };
+
+    assert!(check(12, 1).is_empty());
+    assert!(check(6, 1).is_empty());
+    assert!(check(11, 1).is_empty());
+    // Back at 12 digits after losing 6
+    assert_eq!(check(12, 1), ["comeback"]);
+    assert_eq!(check(26, 100), ["1e25", "crowd"]);
+    assert!(check(30, 100).is_empty());
+    assert_eq!(progress.reached.len(), 3);
+}
+
+#[test]
+fn reached_milestones_are_broadcast() {
+    let mut counter = Counter::new(0);
+    counter.count.value = BigInt::from(10).pow(50);
+    let mut engine = GameEngine::new(counter, EngineConfig::default());
+
+    let output = engine.tick(&TickInput {
+        now: 42,
+        clients: vec![ClientInput::default(); 3],
+        ..Default::default()
+    });
+    let reached: Vec<_> = output
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Milestone(reached) => Some(reached.id.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(reached, ["1e25", "1e50"]);
+    assert!(output.changed);
+    assert_eq!(engine.counter.milestones.reached[1].reached_at, 42);
+}
@@ -0,0 +1,11 @@
+use one_googol::name::{validate, NameError};
+
+#[test]
+fn names_are_validated() {
+    assert_eq!(validate("  Nils_W. 2 ").unwrap(), "Nils_W. 2");
+    assert_eq!(validate("grape analysis").unwrap(), "grape analysis");
+    assert_eq!(validate("   "), Err(NameError::Empty));
+    assert_eq!(validate(&"a".repeat(25)), Err(NameError::TooLong));
+    assert_eq!(validate("a<b"), Err(NameError::InvalidCharacter('<')));
+    assert_eq!(validate("Sh1-t"), Err(NameError::Profanity));
+}
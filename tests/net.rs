@@ -0,0 +1,84 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode},
+};
+use one_googol::{
+    net::{redirect_app, ClientInfo, ProxyConfig},
+    security::AllowedOrigins,
+};
+use tower::ServiceExt;
+
+fn forwarded_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "x-forwarded-for",
+        HeaderValue::from_static("10.0.0.1, 203.0.113.7"),
+    );
+    headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+    headers
+}
+
+#[test]
+fn forwarded_headers_are_only_used_from_trusted_proxies() {
+    let peer = Some("127.0.0.1".parse().unwrap());
+    let headers = forwarded_headers();
+
+    let untrusted = ProxyConfig {
+        tls: false,
+        trust_proxy: false,
+    };
+    assert_eq!(
+        ClientInfo::new(untrusted, peer, &headers),
+        ClientInfo {
+            ip: peer,
+            secure: false
+        }
+    );
+
+    let trusted = ProxyConfig {
+        tls: false,
+        trust_proxy: true,
+    };
+    assert_eq!(
+        ClientInfo::new(trusted, peer, &headers),
+        ClientInfo {
+            ip: Some("203.0.113.7".parse().unwrap()),
+            secure: true
+        }
+    );
+}
+
+#[tokio::test]
+async fn http_is_redirected_to_https() {
+    let request = Request::get("/faq?lang=en")
+        .header(header::HOST, "example.com:80")
+        .body(Body::empty())
+        .unwrap();
+    let response = redirect_app(8443).oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(
+        response.headers()[header::LOCATION],
+        "https://example.com:8443/faq?lang=en"
+    );
+}
+
+#[test]
+fn allowed_origins_are_matched_exactly() {
+    let headers = |origin: &str| {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_static("localhost:8080"));
+        headers.insert(header::ORIGIN, origin.parse().unwrap());
+        headers
+    };
+    let origins = AllowedOrigins::new(&["HTTPS://One-Googol.example/".into()]).unwrap();
+
+    assert!(origins.allows(&headers("https://one-googol.example")));
+    assert!(origins.allows(&headers("http://localhost:8080")));
+    assert!(!origins.allows(&headers("http://one-googol.example")));
+    assert!(!origins.allows(&headers("https://one-googol.example.evil")));
+    assert!(AllowedOrigins::new(&["*".into()])
+        .unwrap()
+        .allows(&headers("https://anything.example")));
+    assert!(AllowedOrigins::new(&["localhost:8080".into()]).is_err());
+}
@@ -0,0 +1,155 @@
+use num_bigint::BigInt;
+use one_googol::{
+    counter::{Counter, Poll},
+    engine::{ClientInput, EngineConfig, GameEngine, TickInput},
+    poll::{PollResult, PollRules, TieBreak, VoteWeighting},
+    upgrade::Catalogue,
+    ws::PollState,
+};
+use std::{path::PathBuf, time::Duration};
+
+/// An engine at the first upgrade, polls last three ticks
+fn engine(poll: PollRules) -> GameEngine {
+    let mut counter = Counter::new(0);
+    counter.count.value = BigInt::from(10).pow(9);
+    let config = EngineConfig {
+        tick: Duration::from_secs(100),
+        poll,
+        ..Default::default()
+    };
+    GameEngine::new(counter, config)
+}
+
+fn vote(id: &str, added: u32) -> ClientInput {
+    let poll_state = match id {
+        "" => PollState::Pending,
+        "-" => PollState::Abstain,
+        id => PollState::Vote(id.into()),
+    };
+    ClientInput {
+        poll_state,
+        added: BigInt::from(added),
+        ..Default::default()
+    }
+}
+
+/// Ticks until the poll finished, at most `limit` times
+fn run_poll(engine: &mut GameEngine, clients: &[ClientInput], limit: usize) -> Option<PollResult> {
+    for _ in 0..limit {
+        engine.tick(&TickInput {
+            clients: clients.to_vec(),
+            ..Default::default()
+        });
+        if engine.counter.last_poll().is_some() {
+            break;
+        }
+    }
+    engine.counter.last_poll().cloned()
+}
+
+#[test]
+fn weighted_votes_and_ties_follow_the_rules() {
+    let mut weighted = engine(PollRules {
+        weighting: VoteWeighting::Contribution,
+        ..Default::default()
+    });
+    let clients = [vote("base", 0), vote("base", 0), vote("exponent", 1000)];
+    let result = run_poll(&mut weighted, &clients, 10).unwrap();
+    assert_eq!(result.winner, "exponent");
+    assert_eq!(result.tally.votes["base"], 2);
+    assert_eq!(result.tally.votes["exponent"], 5);
+    assert!(!result.tie_broken);
+
+    let mut tied = engine(PollRules {
+        tie_break: TieBreak::First,
+        ..Default::default()
+    });
+    let clients = [vote("base", 0), vote("exponent", 0), vote("-", 0)];
+    let result = run_poll(&mut tied, &clients, 10).unwrap();
+    assert_eq!(result.winner, "base");
+    assert_eq!(result.tally.abstain, 1);
+    assert!(result.tie_broken);
+    assert_eq!(tied.counter.upgrade.base, 2);
+}
+
+#[test]
+fn quorum_and_min_runtime_hold_polls_open() {
+    let mut engine = engine(PollRules {
+        quorum: 0.5,
+        min_runtime: Duration::from_secs(800),
+        ..Default::default()
+    });
+    let mut clients = [vote("base", 0), vote("", 0), vote("", 0)];
+    assert_eq!(run_poll(&mut engine, &clients, 20), None);
+    assert!(engine.counter.poll.is_some());
+
+    clients[1].poll_state = PollState::Abstain;
+    let result = run_poll(&mut engine, &clients, 10).unwrap();
+    assert_eq!(result.winner, "base");
+    assert!(result.ticks >= 8);
+
+    let mut short = self::engine(PollRules {
+        min_runtime: Duration::from_secs(800),
+        ..Default::default()
+    });
+    let result = run_poll(&mut short, &clients, 20).unwrap();
+    assert_eq!(result.ticks, 8);
+}
+
+#[test]
+fn polls_can_offer_more_options() {
+    let mut upgrades = Catalogue::default();
+    upgrades.load_from_file(&PathBuf::from("data/upgrades.json"));
+    let mut engine = engine(PollRules {
+        options: 4,
+        ..Default::default()
+    });
+    engine.config.upgrades = upgrades;
+    engine.tick(&TickInput::default());
+    let options = engine.counter.poll.as_ref().unwrap().options.clone();
+    assert_eq!(options.len(), 4);
+    let poll = engine.counter.poll.as_ref().unwrap();
+    assert_eq!((poll.length, poll.tick_ms), (3, 100_000));
+
+    // Votes for upgrades not offered count as pending
+    let clients = [
+        vote(&options[2].id, 0),
+        vote(&options[2].id, 0),
+        vote(&options[3].id, 0),
+        vote("unknown", 0),
+    ];
+    let result = run_poll(&mut engine, &clients, 10).unwrap();
+    assert_eq!(result.winner, options[2].id);
+    assert_eq!(result.tally.votes.len(), 4);
+    assert_eq!(result.tally.pending, 1);
+}
+
+#[test]
+fn tied_polls_pause_by_default() {
+    let mut engine = engine(PollRules::default());
+    let mut clients = [vote("base", 0), vote("exponent", 0), vote("", 0)];
+    assert_eq!(run_poll(&mut engine, &clients, 20), None);
+    let poll = engine.counter.poll.as_ref().unwrap();
+    assert_eq!(
+        poll.time_remaining,
+        Poll::new(Duration::from_secs(100), vec![]).time_remaining
+    );
+
+    clients[2] = vote("exponent", 0);
+    let result = run_poll(&mut engine, &clients, 10).unwrap();
+    assert_eq!(result.winner, "exponent");
+    assert!(!result.tie_broken);
+}
+
+#[test]
+fn polls_tied_at_zero_still_finish() {
+    let mut engine = engine(PollRules::default());
+    engine.tick(&TickInput::default());
+    engine.counter.poll.as_mut().unwrap().time_remaining = 0;
+
+    // Everyone left, the poll ends 0 to 0 like before the poll rules
+    let result = run_poll(&mut engine, &[], 1).unwrap();
+    assert_eq!(result.winner, "exponent");
+    assert!(result.tie_broken);
+    assert!(engine.counter.poll.is_none());
+}
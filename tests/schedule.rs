@@ -0,0 +1,58 @@
+use num_bigint::BigInt;
+use one_googol::{
+    counter::Counter,
+    schedule::{Modifier, Schedule, ScheduledEvent},
+    ws::CounterState,
+};
+
+fn event(id: &str, starts_at: u64, ends_at: u64, modifier: Modifier) -> ScheduledEvent {
+    ScheduledEvent {
+        id: id.into(),
+        name: id.into(),
+        starts_at,
+        ends_at,
+        modifier,
+    }
+}
+
+#[test]
+fn running_events_modify_the_count() {
+    let schedule = Schedule {
+        events: vec![
+            event(
+                "double-step",
+                0,
+                100,
+                Modifier {
+                    step: 200,
+                    ..Default::default()
+                },
+            ),
+            event(
+                "immunity",
+                50,
+                60,
+                Modifier {
+                    decrement: 0,
+                    ..Default::default()
+                },
+            ),
+        ],
+    };
+    assert_eq!(schedule.modifier(100), Modifier::default());
+    let active = schedule.active(55);
+    assert_eq!(active.len(), 2);
+    assert_eq!(active[1].remaining_secs, 5);
+
+    let mut counter = Counter::new(0);
+    counter.count.value = BigInt::from(100);
+    let states = [(CounterState::Increment, 0), (CounterState::Decrement, 0)];
+    let step = counter.update_count(&states, &schedule.modifier(10));
+    assert_eq!(step.increment, BigInt::from(2));
+    assert_eq!(step.decrement, BigInt::from(2));
+    assert_eq!(counter.count.value, BigInt::from(100));
+
+    let step = counter.update_count(&states, &schedule.modifier(55));
+    assert_eq!(step.decrement, BigInt::from(0));
+    assert_eq!(counter.count.value, BigInt::from(102));
+}
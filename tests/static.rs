@@ -0,0 +1,83 @@
+#![cfg(not(feature = "embed"))]
+
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header, Request, Response, StatusCode},
+    Router,
+};
+use common::TestApp;
+use http_body_util::BodyExt;
+use one_googol::{
+    counter::Counter,
+    engine::{EngineConfig, GameEngine},
+};
+use std::fs;
+use tower::ServiceExt;
+
+fn app() -> TestApp {
+    let app = TestApp::new(GameEngine::new(Counter::new(0), EngineConfig::default()));
+    let view = app.view();
+    fs::create_dir_all(view.join("_app/immutable")).unwrap();
+    fs::write(view.join("index.html"), "index").unwrap();
+    fs::write(view.join("faq.html"), "faq").unwrap();
+    fs::write(view.join("404.html"), "not found").unwrap();
+    fs::write(view.join("_app/immutable/app.js"), "app").unwrap();
+    fs::write(view.join("_app/immutable/app.js.br"), "brotli").unwrap();
+    app
+}
+
+async fn get(app: Router, path: &str, encoding: &str) -> (Response<Body>, String) {
+    let request = Request::get(path)
+        .header(header::ACCEPT_ENCODING, encoding)
+        .body(Body::empty())
+        .unwrap();
+    let (parts, body) = app.oneshot(request).await.unwrap().into_parts();
+    let body = body.collect().await.unwrap().to_bytes();
+    (
+        Response::from_parts(parts, Body::empty()),
+        String::from_utf8_lossy(&body).into(),
+    )
+}
+
+#[tokio::test]
+async fn static_files_are_cached_by_kind() {
+    let app = app();
+
+    let (response, body) = get(app.router.clone(), "/faq", "identity").await;
+    assert_eq!(body, "faq");
+    assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+
+    assert_eq!(
+        response.headers()[header::X_CONTENT_TYPE_OPTIONS],
+        "nosniff"
+    );
+    assert!(response.headers()[header::CONTENT_SECURITY_POLICY]
+        .to_str()
+        .unwrap()
+        .contains("frame-ancestors 'none'"));
+    // Plain http never announces HSTS
+    assert!(!response
+        .headers()
+        .contains_key(header::STRICT_TRANSPORT_SECURITY));
+
+    let (response, body) = get(app.router, "/_app/immutable/app.js", "br").await;
+    assert_eq!(body, "brotli");
+    assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "public, max-age=31536000, immutable"
+    );
+}
+
+#[tokio::test]
+async fn unknown_paths_serve_the_404_page() {
+    let app = app();
+
+    for path in ["/missing", "/missing.js", "/../index.html"] {
+        let (response, body) = get(app.router.clone(), path, "identity").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{path}");
+        assert_eq!(body, "not found");
+    }
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::TestServer;
+use num_bigint::BigInt;
+use one_googol::{
+    counter::Counter,
+    engine::{ClientInput, EngineConfig, GameEngine, TickInput},
+    event::Event,
+    schedule::Modifier,
+    season::Completion,
+    team::Teams,
+    ws::{CounterState, PollState},
+};
+use std::time::Duration;
+
+fn client(counter_state: CounterState, vote: &str) -> ClientInput {
+    ClientInput {
+        counter_state,
+        poll_state: PollState::Vote(vote.into()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn teams_vote_their_own_upgrades() {
+    let mut counter = Counter::new(0);
+    counter.count.value = BigInt::from(10).pow(9);
+    let config = EngineConfig {
+        tick: Duration::from_secs(100),
+        teams: true,
+        ..Default::default()
+    };
+    let mut engine = GameEngine::new(counter, config);
+    let input = TickInput {
+        clients: vec![
+            client(CounterState::Increment, "base"),
+            client(CounterState::Increment, "base"),
+            client(CounterState::Decrement, "exponent"),
+        ],
+        ..Default::default()
+    };
+
+    let output = engine.tick(&input);
+    assert!(output.events.contains(&Event::Lead {
+        team: CounterState::Increment
+    }));
+    for _ in 0..10 {
+        engine.tick(&input);
+    }
+
+    let teams = engine.counter.teams.as_ref().unwrap();
+    assert_eq!(teams.leader, CounterState::Increment);
+    assert_eq!(teams.increment.members, 2);
+    assert_eq!(teams.increment.upgrade_path, ["base"]);
+    assert_eq!(teams.increment.upgrade.base, 2);
+    assert_eq!(teams.decrement.upgrade_path, ["exponent"]);
+    assert_eq!(teams.decrement.upgrade.exponent, 1);
+    // Both polls are recorded in the shared history with their team
+    let history = &engine.counter.poll_history;
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].team, Some(CounterState::Increment));
+    assert_eq!(history[0].winner, "base");
+    assert_eq!(history[1].team, Some(CounterState::Decrement));
+    assert_eq!(history[1].winner, "exponent");
+    // The shared upgrades stay untouched
+    assert_eq!(engine.counter.upgrade.base, 1);
+    assert!(engine.counter.poll.is_none());
+}
+
+#[test]
+fn completions_keep_the_upgrades_of_both_teams() {
+    let mut counter = Counter::new(0);
+    counter.teams = Some(Teams::default());
+    counter.teams.as_mut().unwrap().increment.upgrade.base = 3;
+    counter.teams.as_mut().unwrap().decrement.upgrade_path = vec!["exponent".into()];
+    counter.count.value = BigInt::from(10).pow(100);
+
+    let completion = counter.check_completion(10).unwrap();
+    let teams = completion.teams.unwrap();
+    assert_eq!(teams.increment.upgrade.base, 3);
+    assert_eq!(teams.decrement.upgrade_path, ["exponent"]);
+
+    // Completions archived before team mode have no teams
+    let mut saved = serde_json::to_value(Completion::new(&counter, 0)).unwrap();
+    saved.as_object_mut().unwrap().remove("teams");
+    let completion: Completion = serde_json::from_value(saved).unwrap();
+    assert_eq!(completion.teams, None);
+}
+
+#[test]
+fn resistance_shields_a_team_from_the_other() {
+    let mut counter = Counter::new(0);
+    counter.count.value = BigInt::from(100);
+    let mut teams = Teams::default();
+    teams.increment.upgrade.base = 4;
+    teams.increment.upgrade.decrement_resistance = 50;
+    teams.decrement.upgrade.base = 4;
+    teams.decrement.upgrade.decrement_resistance = 75;
+    counter.teams = Some(teams);
+
+    let states = [(CounterState::Increment, 0), (CounterState::Decrement, 0)];
+    let step = counter.update_count(&states, &Modifier::default());
+    // (1 * 4)^1 on both sides, lowered by the resistance of the other team
+    assert_eq!(step.increment, BigInt::from(1));
+    assert_eq!(step.decrement, BigInt::from(2));
+}
+
+#[tokio::test]
+async fn clients_learn_their_team_and_cannot_switch() {
+    let config = EngineConfig {
+        teams: true,
+        ..Default::default()
+    };
+    let server = TestServer::with_engine(GameEngine::new(Counter::new(0), config)).await;
+    let mut client = server.connect("?team=decrement").await;
+    assert_eq!(client.next_event("joined").await["team"], "Decrement");
+
+    client.send("increment").await;
+    let rejected = client.next_event("rejected").await;
+    assert_eq!(
+        rejected["reason"],
+        "teams cannot be switched while connected"
+    );
+    // Picking the own side again is fine
+    client.send("decrement").await;
+    client.send("set_name  ").await;
+    assert_eq!(
+        client.next_event("rejected").await["reason"],
+        "name is empty"
+    );
+    server
+        .wait_for(|c| {
+            c.values()
+                .all(|c| c.counter_state == CounterState::Decrement)
+        })
+        .await;
+}
@@ -0,0 +1,125 @@
+use num_bigint::BigInt;
+use one_googol::{
+    counter::{Counter, Frenzy, Upgrade},
+    engine::{ClientInput, EngineConfig, GameEngine, TickInput},
+    schedule::Modifier,
+    season::Completion,
+    upgrade::{Catalogue, Effect, UpgradeKind},
+    ws::{CounterState, PollState},
+};
+use std::{path::PathBuf, time::Duration};
+
+#[test]
+fn effects_change_the_step() {
+    let mut counter = Counter::new(0);
+    counter.count.value = BigInt::from(100);
+    counter.upgrade.base = 2;
+    counter.upgrade.decrement_resistance = 50;
+    counter.upgrade.tick_boost = 1;
+    counter.upgrade.frenzy = Some(Frenzy {
+        multiplier: 2,
+        remaining_ms: 250,
+    });
+
+    let states = [(CounterState::Increment, 0), (CounterState::Decrement, 0)];
+    let step = counter.update_count(&states, &Modifier::default());
+    // (1 * 2)^1 applied twice and doubled by the frenzy, the decrement halved
+    assert_eq!(step.increment, BigInt::from(8));
+    assert_eq!(step.decrement, BigInt::from(1));
+    assert_eq!(counter.count.value, BigInt::from(107));
+    counter.tick_frenzies(Duration::from_millis(250));
+    assert_eq!(counter.upgrade.frenzy, None);
+}
+
+#[test]
+fn polls_offer_upgrades_from_the_catalogue() {
+    let mut upgrades = Catalogue::default();
+    upgrades.load_from_file(&PathBuf::from("data/upgrades.json"));
+    assert_eq!(upgrades.validate(2), Ok(()));
+    assert_eq!(upgrades.draw(7, 2), upgrades.draw(7, 2));
+
+    let mut counter = Counter::new(0);
+    counter.count.value = BigInt::from(10).pow(9);
+    let config = EngineConfig {
+        tick: Duration::from_secs(100),
+        upgrades,
+        ..Default::default()
+    };
+    let mut engine = GameEngine::new(counter, config);
+    engine.tick(&TickInput::default());
+    let options = engine.counter.poll.as_ref().unwrap().options.clone();
+    assert_eq!(options.len(), 2);
+    let clients = vec![ClientInput {
+        poll_state: PollState::Vote(options[0].id.clone()),
+        ..Default::default()
+    }];
+
+    while engine.counter.poll.is_some() {
+        engine.tick(&TickInput {
+            clients: clients.clone(),
+            ..Default::default()
+        });
+    }
+    let result = engine.counter.last_poll().unwrap().clone();
+    assert_eq!(result.winner, options[0].id);
+    assert_ne!(engine.counter.upgrade, Upgrade::new());
+}
+
+#[test]
+fn poll_states_of_old_saves_are_migrated() {
+    let mut saved = serde_json::to_value(Counter::new(0)).unwrap();
+    saved["upgrade"]["last_upgrade"] = "Pending".into();
+    saved["season"]["upgrade_path"] = serde_json::json!(["Base", "Pending", "Exponent"]);
+    let counter: Counter = serde_json::from_value(saved.clone()).unwrap();
+    assert_eq!(counter.upgrade.last_upgrade, None);
+    assert_eq!(counter.season.upgrade_path, ["base", "exponent"]);
+
+    saved["upgrade"]["last_upgrade"] = "Base".into();
+    let counter: Counter = serde_json::from_value(saved).unwrap();
+    assert_eq!(counter.upgrade.last_upgrade.as_deref(), Some("base"));
+
+    let completion = Completion::new(&counter, 0);
+    let mut saved = serde_json::to_value(completion).unwrap();
+    saved["upgrade_path"] = serde_json::json!(["Exponent", "frenzy"]);
+    let completion: Completion = serde_json::from_value(saved).unwrap();
+    assert_eq!(completion.upgrade_path, ["exponent", "frenzy"]);
+}
+
+#[test]
+fn effects_saturate_and_bad_frenzies_are_rejected() {
+    let mut upgrade = Upgrade::new();
+    Effect::Base { amount: u32::MAX }.apply(&mut upgrade, 3);
+    Effect::TickBoost { steps: u32::MAX }.apply(&mut upgrade, 2);
+    assert_eq!(upgrade.base, u32::MAX);
+    assert_eq!(upgrade.tick_boost, u32::MAX);
+    assert!(upgrade.increment_factor() > BigInt::from(u32::MAX));
+
+    let mut upgrades = Catalogue::default();
+    upgrades.upgrades.push(UpgradeKind {
+        id: "stall".into(),
+        label: "Stall".into(),
+        effect: Effect::Frenzy {
+            multiplier: 0,
+            secs: 10,
+        },
+    });
+    assert!(upgrades.validate(2).is_err());
+}
+
+#[test]
+fn frenzies_last_the_same_time_at_any_tick() {
+    for tick in [Duration::from_millis(100), Duration::from_secs(1)] {
+        let mut upgrade = Upgrade::new();
+        Effect::Frenzy {
+            multiplier: 2,
+            secs: 2,
+        }
+        .apply(&mut upgrade, 1);
+        let mut ticks = 0;
+        while upgrade.frenzy.is_some() {
+            upgrade.tick_frenzy(tick);
+            ticks += 1;
+        }
+        assert_eq!(ticks * tick.as_millis(), 2000);
+    }
+}
@@ -0,0 +1,32 @@
+export const value0 = "one googol";
+export const value1 = "one googol";
+export const value2 = "one googol";
+export const value3 = "one googol";
+export const value4 = "one googol";
+export const value5 = "one googol";
+export const value6 = "one googol";
+export const value7 = "one googol";
+export const value8 = "one googol";
+export const value9 = "one googol";
+export const value10 = "one googol";
+export const value11 = "one googol";
+export const value12 = "one googol";
+export const value13 = "one googol";
+export const value14 = "one googol";
+export const value15 = "one googol";
+export const value16 = "one googol";
+export const value17 = "one googol";
+export const value18 = "one googol";
+export const value19 = "one googol";
+export const value20 = "one googol";
+export const value21 = "one googol";
+export const value22 = "one googol";
+export const value23 = "one googol";
+export const value24 = "one googol";
+export const value25 = "one googol";
+export const value26 = "one googol";
+export const value27 = "one googol";
+export const value28 = "one googol";
+export const value29 = "one googol";
+export const value30 = "one googol";
+export const value31 = "one googol";
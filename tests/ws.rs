@@ -0,0 +1,189 @@
+mod common;
+
+use common::TestServer;
+use num_bigint::BigInt;
+use one_googol::{
+    counter::Counter,
+    engine::{EngineConfig, GameEngine},
+    util,
+    ws::{CounterState, PollState},
+};
+
+#[tokio::test]
+async fn increment_is_broadcast() {
+    let server = TestServer::start().await;
+    let mut client = server.connect("").await;
+
+    client.send("increment").await;
+    server
+        .wait_for(|c| {
+            c.values()
+                .all(|c| c.counter_state == CounterState::Increment)
+        })
+        .await;
+    server.tick(0).await;
+
+    let counter = client.next_counter().await;
+    assert_eq!(counter.count.value, BigInt::from(1));
+    assert_eq!(counter.count.meter.increment, 1);
+}
+
+#[tokio::test]
+async fn actions_add_modifier() {
+    let server = TestServer::start().await;
+    let mut client = server.connect("").await;
+
+    client.send("increment").await;
+    for _ in 0..3 {
+        client.send("action").await;
+    }
+    server
+        .wait_for(|c| c.values().all(|c| c.action_clicks == 3))
+        .await;
+    server.tick(0).await;
+
+    let counter = client.next_counter().await;
+    assert_eq!(counter.count.accumulated_actions, 3);
+    assert_eq!(counter.count.value, BigInt::from(4));
+}
+
+#[tokio::test]
+async fn decrement_stops_at_zero() {
+    let server = TestServer::start().await;
+    let mut client = server.connect("").await;
+
+    client.send("decrement").await;
+    server
+        .wait_for(|c| {
+            c.values()
+                .all(|c| c.counter_state == CounterState::Decrement)
+        })
+        .await;
+    server.tick(0).await;
+
+    let counter = client.next_counter().await;
+    assert_eq!(counter.count.value, BigInt::from(0));
+    assert_eq!(counter.count.meter.decrement, 1);
+}
+
+#[tokio::test]
+async fn all_clients_receive_the_same_state() {
+    let server = TestServer::start().await;
+    let mut first = server.connect("").await;
+    let mut second = server.connect("").await;
+
+    first.send("increment").await;
+    second.send("increment").await;
+    server
+        .wait_for(|c| {
+            c.values()
+                .all(|c| c.counter_state == CounterState::Increment)
+        })
+        .await;
+    server.tick(0).await;
+
+    let counter = first.next_counter().await;
+    assert_eq!(counter, second.next_counter().await);
+    // Two incrementers step by (2 * base)^1
+    assert_eq!(counter.count.value, BigInt::from(2));
+}
+
+#[tokio::test]
+async fn completion_is_archived() {
+    let mut counter = Counter::new(0);
+    counter.count.value = BigInt::parse_bytes(util::ONE_GOOGOL.as_bytes(), 10).unwrap() - 1;
+    counter.upgrade.level = 10;
+    let server = TestServer::with_engine(GameEngine::new(counter, EngineConfig::default())).await;
+    let mut client = server.connect("").await;
+
+    client.send("increment").await;
+    server
+        .wait_for(|c| {
+            c.values()
+                .all(|c| c.counter_state == CounterState::Increment)
+        })
+        .await;
+    server.tick(100).await;
+
+    let event = client.next_event("completed").await;
+    assert_eq!(event["completed_at"], 100);
+    assert!(client.next_counter().await.is_googol());
+
+    let (status, body) = server.get("/api/seasons").await;
+    assert_eq!(status, 200);
+    let seasons: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(seasons.as_array().unwrap().len(), 1);
+    assert!(server.hall_of_fame().exists());
+}
+
+#[tokio::test]
+async fn foreign_origins_are_rejected() {
+    use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Error};
+
+    let server = TestServer::start().await;
+    let connect = |origin: String| {
+        let mut request = format!("ws://{}/ws", server.addr)
+            .into_client_request()
+            .unwrap();
+        request
+            .headers_mut()
+            .insert("origin", origin.parse().unwrap());
+        tokio_tungstenite::connect_async(request)
+    };
+
+    let result = connect("https://evil.example".into()).await;
+    assert!(matches!(result, Err(Error::Http(response)) if response.status() == 403));
+    assert!(connect(format!("http://{}", server.addr)).await.is_ok());
+}
+
+#[tokio::test]
+async fn finished_polls_are_served() {
+    let mut counter = Counter::new(0);
+    counter.count.value = BigInt::from(10).pow(9);
+    let config = EngineConfig {
+        tick: std::time::Duration::from_secs(100),
+        ..Default::default()
+    };
+    let server = TestServer::with_engine(GameEngine::new(counter, config)).await;
+    let mut client = server.connect("").await;
+    server.tick(0).await;
+    assert_eq!(client.next_counter().await.poll.unwrap().options.len(), 2);
+
+    // Only offered upgrades can be voted
+    client.send("vote <b>hello</b>").await;
+    assert_eq!(
+        client.next_event("rejected").await["reason"],
+        "invalid upgrade id"
+    );
+    client.send("vote base").await;
+    server
+        .wait_for(|c| {
+            c.values()
+                .all(|c| c.poll_state == PollState::Vote("base".into()))
+        })
+        .await;
+    for now in 1..3 {
+        server.tick(now).await;
+    }
+
+    let (status, body) = server.get("/api/polls").await;
+    assert_eq!(status, 200);
+    let polls: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(polls[0]["winner"], "base");
+    assert_eq!(polls[0]["tally"]["votes"]["base"], 1);
+    assert_eq!(polls[0]["level"], 1);
+    assert_eq!(polls[0]["base"], 2);
+    // Started at the first tick, before the vote
+    assert_eq!(polls[0]["duration_ms"], 300_000);
+}
+
+#[tokio::test]
+async fn upgrades_are_served_with_their_labels() {
+    let server = TestServer::start().await;
+    let (status, body) = server.get("/api/upgrades").await;
+    assert_eq!(status, 200);
+    let upgrades: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(upgrades[0]["id"], "base");
+    assert_eq!(upgrades[0]["label"], "Base");
+    assert_eq!(upgrades[1]["effect"]["kind"], "exponent");
+}
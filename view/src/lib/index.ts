@@ -2,3 +2,6 @@ import { writable } from 'svelte/store';
 
 export const increaseType = writable<string>('');
 export const pollType = writable<string>('');
+
+/** Poll type of abstaining, no upgrade in the catalogue uses this id */
+export const ABSTAIN = 'abstain';
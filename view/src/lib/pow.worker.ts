@@ -0,0 +1,32 @@
+/** Hashes computed concurrently before checking for a solution */
+const BATCH = 256;
+
+/**
+ * Solves a join challenge off the main thread: posts back the first number whose
+ * `sha256("<challenge>:<number>")` starts with `difficulty` zero bits.
+ */
+onmessage = async (event: MessageEvent<{ challenge: string; difficulty: number }>) => {
+	let { challenge, difficulty } = event.data;
+	let encoder = new TextEncoder();
+	for (let start = 0; ; start += BATCH) {
+		let hashes = await Promise.all(
+			Array.from({ length: BATCH }, (_, i) =>
+				crypto.subtle.digest('SHA-256', encoder.encode(`${challenge}:${start + i}`))
+			)
+		);
+		let index = hashes.findIndex((hash) => leadingZeros(new Uint8Array(hash)) >= difficulty);
+		if (index >= 0) {
+			postMessage(start + index);
+			return;
+		}
+	}
+};
+
+function leadingZeros(hash: Uint8Array): number {
+	let zeros = 0;
+	for (let byte of hash) {
+		if (byte != 0) return zeros + Math.clz32(byte) - 24;
+		zeros += 8;
+	}
+	return zeros;
+}
@@ -6,7 +6,8 @@ const config = {
 	preprocess: vitePreprocess({ script: true }),
 	kit: {
 		adapter: adapter({
-			fallback: '404.html'
+			fallback: '404.html',
+			precompress: true
 		})
 	}
 };